@@ -2,7 +2,7 @@ use std::fs::read_to_string;
 
 use crate::utils::get_input_file_name;
 
-fn check_number_word(chars: &Vec<char>, index: usize, length: usize, expected_number: i64) -> bool {
+fn check_number_word(chars: &[char], index: usize, length: usize, expected_number: i64) -> bool {
     if chars.len() < index + length {
         return false;
     }
@@ -21,7 +21,7 @@ fn check_number_word(chars: &Vec<char>, index: usize, length: usize, expected_nu
     }
 }
 
-fn is_number(chars: &Vec<char>, i: usize) -> Option<i64> {
+fn is_number(chars: &[char], i: usize) -> Option<i64> {
     if chars[i].is_numeric() {
         return Some(chars[i].to_digit(10).unwrap() as i64);
     }
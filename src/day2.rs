@@ -1,11 +1,67 @@
+use std::collections::BTreeMap;
 use std::fs::read_to_string;
+use std::io::Error;
 
 use crate::utils::get_input_file_name;
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Color {
+    Red,
+    Green,
+    Blue,
+}
+
+impl Color {
+    const ALL: [Color; 3] = [Color::Red, Color::Green, Color::Blue];
+
+    fn name(self) -> &'static str {
+        match self {
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Blue => "blue",
+        }
+    }
+
+    fn bag_limit(self) -> u64 {
+        match self {
+            Color::Red => RED_CUBES,
+            Color::Green => GREEN_CUBES,
+            Color::Blue => BLUE_CUBES,
+        }
+    }
+}
+
+// A colour that does not appear in a set was drawn zero times.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 struct Set {
-    green: i64,
-    blue: i64,
-    red: i64,
+    green: u64,
+    blue: u64,
+    red: u64,
+}
+
+impl Set {
+    fn count(&self, color: Color) -> u64 {
+        match color {
+            Color::Red => self.red,
+            Color::Green => self.green,
+            Color::Blue => self.blue,
+        }
+    }
+
+    fn count_mut(&mut self, color: Color) -> &mut u64 {
+        match color {
+            Color::Red => &mut self.red,
+            Color::Green => &mut self.green,
+            Color::Blue => &mut self.blue,
+        }
+    }
+
+    // None when the product does not fit in a u64.
+    fn power(&self) -> Option<u64> {
+        Color::ALL
+            .iter()
+            .try_fold(1u64, |acc, color| acc.checked_mul(self.count(*color)))
+    }
 }
 
 struct Game {
@@ -13,9 +69,9 @@ struct Game {
     sets: Vec<Set>,
 }
 
-const RED_CUBES: i64 = 12;
-const GREEN_CUBES: i64 = 13;
-const BLUE_CUBES: i64 = 14;
+const RED_CUBES: u64 = 12;
+const GREEN_CUBES: u64 = 13;
+const BLUE_CUBES: u64 = 14;
 
 // Game 6: 8 blue, 1 red, 17 green; 7 blue; 10 green, 6 blue; 5 blue, 1 red, 11 green
 fn parse_line(line: &str) -> Game {
@@ -25,14 +81,10 @@ fn parse_line(line: &str) -> Game {
 
     let sets = parts[1].split(';').map(|set_string| {
         let set_parts = set_string.split(',').collect::<Vec<&str>>();
-        let mut set = Set {
-            green: 0,
-            blue: 0,
-            red: 0,
-        };
+        let mut set = Set::default();
         for set_part in set_parts {
             let set_part_parts = set_part.trim().split(' ').collect::<Vec<&str>>();
-            let number = set_part_parts[0].parse::<i64>().unwrap();
+            let number = set_part_parts[0].parse::<u64>().unwrap();
             let color = set_part_parts[1].trim();
             match color {
                 "green" => set.green = number,
@@ -51,47 +103,171 @@ fn parse_line(line: &str) -> Game {
 
 fn is_game_valid(game: &Game) -> bool {
     game.sets.iter().all(|set| {
-        set.green <= GREEN_CUBES && set.blue <= BLUE_CUBES && set.red <= RED_CUBES
+        Color::ALL
+            .iter()
+            .all(|color| set.count(*color) <= color.bag_limit())
     })
 }
 
-fn fewest_possible_cubes(game: &Game) -> Set {
-    let mut max_red = i64::MIN;
-    let mut max_green = i64::MIN;
-    let mut max_blue = i64::MIN;
-    for set in game.sets.iter() {
-        if set.red > max_red {
-            max_red = set.red;
+// The first draw of a game that needed more cubes of one colour than the bag holds.
+#[derive(Debug, PartialEq)]
+struct Disqualification {
+    set_index: usize,
+    color: Color,
+    count: u64,
+}
+
+impl Disqualification {
+    fn excess(&self) -> u64 {
+        self.count - self.color.bag_limit()
+    }
+}
+
+fn first_disqualification(game: &Game) -> Option<Disqualification> {
+    game.sets.iter().enumerate().find_map(|(set_index, set)| {
+        Color::ALL
+            .into_iter()
+            .find(|color| set.count(*color) > color.bag_limit())
+            .map(|color| Disqualification {
+                set_index,
+                color,
+                count: set.count(color),
+            })
+    })
+}
+
+#[derive(Debug, Default)]
+struct ColorStats {
+    max: u64,
+    total: u64,
+    draws: usize,
+    // number of cubes drawn -> how many draws showed that many
+    histogram: BTreeMap<u64, usize>,
+}
+
+impl ColorStats {
+    fn record(&mut self, count: u64) {
+        // a colour missing from a set is stored as 0, which is not a draw
+        if count == 0 {
+            return;
         }
-        if set.green > max_green {
-            max_green = set.green;
+        self.max = self.max.max(count);
+        self.total += count;
+        self.draws += 1;
+        *self.histogram.entry(count).or_insert(0) += 1;
+    }
+
+    fn mean(&self) -> f64 {
+        if self.draws == 0 {
+            0.
+        } else {
+            self.total as f64 / self.draws as f64
         }
-        if set.blue > max_blue {
-            max_blue = set.blue;
+    }
+}
+
+fn color_stats(games: &[Game]) -> [(Color, ColorStats); 3] {
+    let mut stats = Color::ALL.map(|color| (color, ColorStats::default()));
+    for set in games.iter().flat_map(|game| game.sets.iter()) {
+        for (color, color_stats) in stats.iter_mut() {
+            color_stats.record(set.count(*color));
         }
     }
-    Set {
-        green: max_green,
-        blue: max_blue,
-        red: max_red,
+    stats
+}
+
+// The smallest bag that would have made every game possible.
+fn minimum_bag(games: &[Game]) -> Set {
+    let sets = games.iter().map(fewest_possible_cubes).collect::<Vec<Set>>();
+    max_per_color(&sets)
+}
+
+fn max_per_color(sets: &[Set]) -> Set {
+    let mut res = Set::default();
+    for set in sets.iter() {
+        for color in Color::ALL {
+            let max = res.count_mut(color);
+            *max = (*max).max(set.count(color));
+        }
     }
+    res
+}
+
+// A game without sets, or a colour that never shows up, needs zero cubes of that colour.
+fn fewest_possible_cubes(game: &Game) -> Set {
+    max_per_color(&game.sets)
 }
 
 pub fn solve() {
     let mut valid_games_sum = 0;
-    let mut power_sum = 0;
+    let mut power_sum: Option<u64> = Some(0);
     let input_file_name = get_input_file_name(module_path!());
     for line in read_to_string(format!("src/{}", input_file_name)).unwrap().lines() {
         let game = parse_line(line);
         if is_game_valid(&game) {
             valid_games_sum += game.id;
         }
-        let fewest_possible_cubes = fewest_possible_cubes(&game);
-        let power = fewest_possible_cubes.green * fewest_possible_cubes.blue * fewest_possible_cubes.red;
-        power_sum += power;
+        let power = fewest_possible_cubes(&game).power();
+        if power.is_none() {
+            println!("module: {}, power of game {} overflows", module_path!(), game.id);
+        }
+        power_sum = power_sum
+            .zip(power)
+            .and_then(|(sum, power)| sum.checked_add(power));
     }
     println!("module: {}, Part 1 result: {}", module_path!(), valid_games_sum);
-    println!("module: {}, Part 2 result: {}", module_path!(), power_sum);
+    match power_sum {
+        Some(power_sum) => println!("module: {}, Part 2 result: {}", module_path!(), power_sum),
+        None => println!("module: {}, Part 2 result: overflow", module_path!()),
+    }
+}
+
+pub fn analyze() -> Result<(), Error> {
+    let input_file_name = get_input_file_name(module_path!());
+    let games = read_to_string(format!("src/{}", input_file_name))?
+        .lines()
+        .map(parse_line)
+        .collect::<Vec<Game>>();
+
+    println!(
+        "bag: {} red, {} green, {} blue",
+        RED_CUBES, GREEN_CUBES, BLUE_CUBES
+    );
+    println!("impossible games:");
+    for game in games.iter() {
+        if let Some(d) = first_disqualification(game) {
+            println!(
+                "  game {}: set {} drew {} {} (bag has {}, {} too many)",
+                game.id,
+                d.set_index + 1,
+                d.count,
+                d.color.name(),
+                d.color.bag_limit(),
+                d.excess()
+            );
+        }
+    }
+
+    println!("colour statistics across {} games:", games.len());
+    for (color, stats) in color_stats(&games).iter() {
+        println!(
+            "  {}: max {}, mean {:.2} over {} draws",
+            color.name(),
+            stats.max,
+            stats.mean(),
+            stats.draws
+        );
+        for (count, draws) in stats.histogram.iter() {
+            println!("    {:>3} | {} {}", count, "#".repeat(*draws), draws);
+        }
+    }
+
+    let bag = minimum_bag(&games);
+    println!(
+        "minimum bag for every game: {} red, {} green, {} blue",
+        bag.red, bag.green, bag.blue
+    );
+    Ok(())
 }
 
 #[test]
@@ -145,4 +321,86 @@ fn test_fewest_possible_cubes() {
         assert_eq!(fewest_possible_cubes.green, case.2);
         assert_eq!(fewest_possible_cubes.blue, case.3);
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_first_disqualification() {
+    let game = parse_line("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green");
+    assert_eq!(first_disqualification(&game), None);
+
+    let game =
+        parse_line("Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red");
+    let d = first_disqualification(&game).unwrap();
+    assert_eq!(
+        d,
+        Disqualification {
+            set_index: 0,
+            color: Color::Red,
+            count: 20,
+        }
+    );
+    assert_eq!(d.excess(), 8);
+
+    let game =
+        parse_line("Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red");
+    let d = first_disqualification(&game).unwrap();
+    assert_eq!(d.set_index, 2);
+    assert_eq!(d.color, Color::Red);
+    assert_eq!(d.excess(), 2);
+}
+
+#[test]
+fn test_color_stats_and_minimum_bag() {
+    let games = [
+        "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green",
+        "Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue",
+        "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red",
+    ]
+    .iter()
+    .map(|line| parse_line(line))
+    .collect::<Vec<Game>>();
+
+    let stats = color_stats(&games);
+    let (color, red) = &stats[0];
+    assert_eq!(*color, Color::Red);
+    assert_eq!(red.max, 20);
+    assert_eq!(red.draws, 6);
+    assert_eq!(red.mean(), 31. / 6.);
+    assert_eq!(red.histogram.get(&1), Some(&3));
+    assert_eq!(red.histogram.get(&4), Some(&2));
+
+    let bag = minimum_bag(&games);
+    assert_eq!((bag.red, bag.green, bag.blue), (20, 13, 6));
+}
+
+#[test]
+fn test_fewest_possible_cubes_for_partial_games() {
+    let game = Game {
+        id: 1,
+        sets: vec![],
+    };
+    let fewest = fewest_possible_cubes(&game);
+    assert_eq!(fewest, Set::default());
+    assert_eq!(fewest.power(), Some(0));
+
+    let game = parse_line("Game 2: 3 blue; 4 blue, 1 red");
+    let fewest = fewest_possible_cubes(&game);
+    assert_eq!((fewest.red, fewest.green, fewest.blue), (1, 0, 4));
+    assert_eq!(fewest.power(), Some(0));
+}
+
+#[test]
+fn test_power_overflow() {
+    let set = Set {
+        green: 6,
+        blue: 2,
+        red: 4,
+    };
+    assert_eq!(set.power(), Some(48));
+
+    let set = Set {
+        green: u64::MAX,
+        blue: 2,
+        red: 1,
+    };
+    assert_eq!(set.power(), None);
+}
@@ -1,8 +1,96 @@
-use std::{collections::HashSet, fs::read_to_string};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    fs::read_to_string,
+    io::{Error, ErrorKind, IsTerminal},
+};
 
 use crate::utils::get_input_file_name;
 
-fn scan_number(line: &Vec<u8>, mut i: usize) -> usize {
+// Which bytes of a raw schematic are empty space and which are symbols. Digits are always
+// numbers, whitespace at the end of a row is ignored and anything else is rejected.
+#[derive(Debug)]
+struct SchematicSyntax {
+    filler: Vec<u8>,
+    // None accepts every printable byte that is neither a digit nor filler as a symbol
+    symbols: Option<Vec<u8>>,
+}
+
+impl Default for SchematicSyntax {
+    fn default() -> Self {
+        SchematicSyntax {
+            filler: vec![b'.'],
+            symbols: None,
+        }
+    }
+}
+
+impl SchematicSyntax {
+    // Maps a raw byte onto the canonical schematic alphabet, where `.` is the only filler.
+    fn canonical(&self, byte: u8) -> Option<u8> {
+        if byte.is_ascii_digit() {
+            Some(byte)
+        } else if self.filler.contains(&byte) {
+            Some(b'.')
+        } else {
+            let is_symbol = match &self.symbols {
+                Some(symbols) => symbols.contains(&byte),
+                None => byte.is_ascii_graphic(),
+            };
+            is_symbol.then_some(byte)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum SchematicError {
+    // row and column are 1-based, as shown by an editor
+    InvalidByte { row: usize, col: usize, byte: u8 },
+}
+
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchematicError::InvalidByte { row, col, byte } => write!(
+                f,
+                "invalid schematic byte {:?} at row {}, column {}",
+                *byte as char, row, col
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SchematicError {}
+
+// Reads a schematic into a rectangular grid in the canonical alphabet. CRLF line endings and
+// trailing whitespace are dropped and ragged rows are padded with filler.
+fn parse_schematic(input: &str, syntax: &SchematicSyntax) -> Result<Vec<Vec<u8>>, SchematicError> {
+    let mut engine_schematic = input
+        .lines()
+        .enumerate()
+        .map(|(row, line)| {
+            line.trim_end()
+                .bytes()
+                .enumerate()
+                .map(|(col, byte)| {
+                    syntax.canonical(byte).ok_or(SchematicError::InvalidByte {
+                        row: row + 1,
+                        col: col + 1,
+                        byte,
+                    })
+                })
+                .collect::<Result<Vec<u8>, SchematicError>>()
+        })
+        .collect::<Result<Vec<Vec<u8>>, SchematicError>>()?;
+
+    let width = engine_schematic.iter().map(Vec::len).max().unwrap_or(0);
+    for line in engine_schematic.iter_mut() {
+        line.resize(width, b'.');
+    }
+    Ok(engine_schematic)
+}
+
+fn scan_number(line: &[u8], mut i: usize) -> usize {
     while i < line.len() && line[i].is_ascii_digit() {
         i += 1;
     }
@@ -14,7 +102,7 @@ fn is_symbol(character: u8) -> bool {
 }
 
 fn is_part_number(
-    engine_schematic: &Vec<Vec<u8>>,
+    engine_schematic: &[Vec<u8>],
     line_num: usize,
     num_start: usize,
     num_end: usize,
@@ -24,7 +112,7 @@ fn is_part_number(
         return true;
     }
     // check right cell
-    if num_end < engine_schematic[line_num].len() - 1
+    if num_end + 1 < engine_schematic[line_num].len()
         && is_symbol(engine_schematic[line_num][num_end + 1])
     {
         return true;
@@ -45,7 +133,7 @@ fn is_part_number(
         }
     }
     // check below adjacent cells
-    if line_num < engine_schematic.len() - 1 {
+    if line_num + 1 < engine_schematic.len() {
         let next_line_num = line_num + 1;
         let mut i = if num_start == 0 {
             num_start
@@ -62,11 +150,31 @@ fn is_part_number(
     false
 }
 
+// Maps every cell adjacent to a part number to the indexes of those part numbers, so the
+// numbers touching a symbol are a single lookup away.
+#[derive(Debug, Default)]
+struct PartIndex {
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl PartIndex {
+    fn insert(&mut self, part_index: usize, part_number: PartNumber) {
+        for cell in part_number.adjacent_cells() {
+            self.cells.entry(cell).or_default().push(part_index);
+        }
+    }
+
+    fn adjacent_parts(&self, cell: &(i64, i64)) -> &[usize] {
+        self.cells.get(cell).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
 fn extract_part_numbers_and_gears(
-    engine_schematic: &Vec<Vec<u8>>,
-) -> (Vec<PartNumber>, HashSet<(i64, i64)>) {
+    engine_schematic: &[Vec<u8>],
+) -> (Vec<PartNumber>, BTreeMap<(i64, i64), u8>, PartIndex) {
     let mut part_numbers = Vec::<PartNumber>::new();
-    let mut gears = HashSet::<(i64, i64)>::new();
+    let mut symbols = BTreeMap::<(i64, i64), u8>::new();
+    let mut index = PartIndex::default();
 
     let mut row = 0;
     while row < engine_schematic.len() {
@@ -79,58 +187,152 @@ fn extract_part_numbers_and_gears(
                         .unwrap()
                         .parse::<i64>()
                         .unwrap();
-                    part_numbers.push(PartNumber {
+                    let part_number = PartNumber {
                         num,
                         line_num: row,
                         num_start: col,
                         num_end: j,
-                    });
+                    };
+                    index.insert(part_numbers.len(), part_number);
+                    part_numbers.push(part_number);
                 }
                 col = j + 1;
-            } else if engine_schematic[row][col] == b'*' {
-                gears.insert((row as i64, col as i64));
-                col += 1;
             } else {
+                if is_symbol(engine_schematic[row][col]) {
+                    symbols.insert((row as i64, col as i64), engine_schematic[row][col]);
+                }
                 col += 1;
             }
         }
         row += 1;
     }
-    (part_numbers, gears)
+    (part_numbers, symbols, index)
+}
+
+// Every `symbol` that touches exactly `n` part numbers, with the part numbers it touches.
+fn symbols_adjacent_to_n_parts<'a>(
+    symbol: u8,
+    n: usize,
+    part_numbers: &'a [PartNumber],
+    symbols: &BTreeMap<(i64, i64), u8>,
+    index: &PartIndex,
+) -> Vec<((i64, i64), Vec<&'a PartNumber>)> {
+    symbols
+        .iter()
+        .filter(|(_, s)| **s == symbol)
+        .filter_map(|(cell, _)| {
+            let neighbours = index.adjacent_parts(cell);
+            if neighbours.len() == n {
+                Some((
+                    *cell,
+                    neighbours.iter().map(|i| &part_numbers[*i]).collect(),
+                ))
+            } else {
+                None
+            }
+        })
+        .collect()
 }
 
-pub fn solve() {
+pub fn solve() -> Result<(), Error> {
     let input_file_name = get_input_file_name(module_path!());
-    let engine_schematic = read_to_string(format!("src/{}", input_file_name))
-        .unwrap()
-        .lines()
-        .map(|line| line.to_string().into_bytes())
-        .collect::<Vec<Vec<u8>>>();
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    let engine_schematic = parse_schematic(&contents, &SchematicSyntax::default())
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
 
-    let (part_numbers, gears) = extract_part_numbers_and_gears(&engine_schematic);
+    let (part_numbers, symbols, index) = extract_part_numbers_and_gears(&engine_schematic);
 
     let parts_sum: i64 = part_numbers.iter().map(|pn| pn.num).sum();
     println!("module: {}, part 1, result: {}", module_path!(), parts_sum);
 
-    let gear_ratios_sum: i64 = gears
-        .iter()
-        .filter_map(|gear| {
-            let neigbours = part_numbers
-                .iter()
-                .filter(|pn| pn.to_owned().adjacent_cells().contains(gear))
-                .collect::<Vec<&PartNumber>>();
-            if neigbours.len() == 2 {
-                Some(neigbours[0].num * neigbours[1].num)
-            } else {
-                None
-            }
-        })
-        .sum();
+    let gear_ratios_sum: i64 =
+        symbols_adjacent_to_n_parts(b'*', 2, &part_numbers, &symbols, &index)
+            .iter()
+            .map(|(_, neighbours)| neighbours[0].num * neighbours[1].num)
+            .sum();
     println!(
         "module: {}, part 2, result: {}",
         module_path!(),
         gear_ratios_sum
     );
+    Ok(())
+}
+
+const PART_NUMBER_COLOR: &str = "\x1b[32m";
+const NON_PART_NUMBER_COLOR: &str = "\x1b[31m";
+const GEAR_COLOR: &str = "\x1b[1;33m";
+const RESET_COLOR: &str = "\x1b[0m";
+
+// Draws the schematic with part numbers, numbers that are not parts and gears (`*` touching
+// exactly two part numbers) in different colours. Without colour the schematic is unchanged.
+fn render_schematic(
+    engine_schematic: &[Vec<u8>],
+    part_numbers: &[PartNumber],
+    gears: &[((i64, i64), Vec<&PartNumber>)],
+    color: bool,
+) -> String {
+    let part_cells = part_numbers
+        .iter()
+        .flat_map(|pn| (pn.num_start..=pn.num_end).map(|col| (pn.line_num, col)))
+        .collect::<HashSet<(usize, usize)>>();
+    let gear_cells = gears
+        .iter()
+        .map(|(cell, _)| (cell.0 as usize, cell.1 as usize))
+        .collect::<HashSet<(usize, usize)>>();
+
+    let mut res = String::new();
+    for (row, line) in engine_schematic.iter().enumerate() {
+        for (col, byte) in line.iter().enumerate() {
+            let cell_color = if !color {
+                None
+            } else if part_cells.contains(&(row, col)) {
+                Some(PART_NUMBER_COLOR)
+            } else if byte.is_ascii_digit() {
+                Some(NON_PART_NUMBER_COLOR)
+            } else if gear_cells.contains(&(row, col)) {
+                Some(GEAR_COLOR)
+            } else {
+                None
+            };
+            match cell_color {
+                Some(cell_color) => {
+                    res.push_str(cell_color);
+                    res.push(*byte as char);
+                    res.push_str(RESET_COLOR);
+                }
+                None => res.push(*byte as char),
+            }
+        }
+        res.push('\n');
+    }
+    res
+}
+
+pub fn show() -> Result<(), Error> {
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    let engine_schematic = parse_schematic(&contents, &SchematicSyntax::default())
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    let (part_numbers, symbols, index) = extract_part_numbers_and_gears(&engine_schematic);
+    let gears = symbols_adjacent_to_n_parts(b'*', 2, &part_numbers, &symbols, &index);
+
+    let color = std::io::stdout().is_terminal();
+    print!(
+        "{}",
+        render_schematic(&engine_schematic, &part_numbers, &gears, color)
+    );
+    for ((row, col), neighbours) in gears.iter() {
+        println!(
+            "gear at row {}, column {}: {} * {} = {}",
+            row + 1,
+            col + 1,
+            neighbours[0].num,
+            neighbours[1].num,
+            neighbours[0].num * neighbours[1].num
+        );
+    }
+    Ok(())
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -250,3 +452,121 @@ fn test_is_part_number() {
     ];
     assert!(is_part_number(&input, 1, 6, 8));
 }
+
+#[test]
+fn test_symbols_adjacent_to_n_parts() {
+    let input = vec![
+        "467..114..".to_string().into_bytes(),
+        "...*......".to_string().into_bytes(),
+        "..35..633.".to_string().into_bytes(),
+        "......#...".to_string().into_bytes(),
+        "617*......".to_string().into_bytes(),
+        ".....+.58.".to_string().into_bytes(),
+        "..592.....".to_string().into_bytes(),
+        "......755.".to_string().into_bytes(),
+        "...$.*....".to_string().into_bytes(),
+        ".664.598..".to_string().into_bytes(),
+    ];
+    let (part_numbers, symbols, index) = extract_part_numbers_and_gears(&input);
+    assert_eq!(part_numbers.iter().map(|pn| pn.num).sum::<i64>(), 4361);
+
+    let gears = symbols_adjacent_to_n_parts(b'*', 2, &part_numbers, &symbols, &index);
+    let ratios = gears
+        .iter()
+        .map(|(cell, neighbours)| (*cell, neighbours[0].num * neighbours[1].num))
+        .collect::<Vec<((i64, i64), i64)>>();
+    assert_eq!(ratios, vec![((1, 3), 16345), ((8, 5), 451490)]);
+
+    let lonely_stars = symbols_adjacent_to_n_parts(b'*', 1, &part_numbers, &symbols, &index);
+    assert_eq!(lonely_stars.len(), 1);
+    assert_eq!(lonely_stars[0].0, (4, 3));
+    assert_eq!(lonely_stars[0].1[0].num, 617);
+
+    let hashes = symbols_adjacent_to_n_parts(b'#', 1, &part_numbers, &symbols, &index);
+    assert_eq!(hashes.len(), 1);
+    assert_eq!(hashes[0].1[0].num, 633);
+}
+
+#[test]
+fn test_parse_schematic() {
+    let syntax = SchematicSyntax::default();
+    let engine_schematic = parse_schematic("467..\r\n...*  \r\n.35\n", &syntax).unwrap();
+    assert_eq!(
+        engine_schematic,
+        vec![
+            "467..".to_string().into_bytes(),
+            "...*.".to_string().into_bytes(),
+            ".35..".to_string().into_bytes(),
+        ]
+    );
+    let (part_numbers, _, _) = extract_part_numbers_and_gears(&engine_schematic);
+    assert_eq!(
+        part_numbers.iter().map(|pn| pn.num).collect::<Vec<i64>>(),
+        vec![467, 35]
+    );
+
+    assert_eq!(
+        parse_schematic("467..\n.. *.", &syntax),
+        Err(SchematicError::InvalidByte {
+            row: 2,
+            col: 3,
+            byte: b' ',
+        })
+    );
+}
+
+#[test]
+fn test_parse_schematic_with_custom_syntax() {
+    let syntax = SchematicSyntax {
+        filler: vec![b' ', b'_'],
+        symbols: Some(vec![b'*', b'#']),
+    };
+    let engine_schematic = parse_schematic("12 _\n  * \n_7#", &syntax).unwrap();
+    assert_eq!(
+        engine_schematic,
+        vec![
+            "12..".to_string().into_bytes(),
+            "..*.".to_string().into_bytes(),
+            ".7#.".to_string().into_bytes(),
+        ]
+    );
+
+    let error = parse_schematic("12.\n..$", &syntax).unwrap_err();
+    assert_eq!(
+        error,
+        SchematicError::InvalidByte {
+            row: 1,
+            col: 3,
+            byte: b'.',
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "invalid schematic byte '.' at row 1, column 3"
+    );
+}
+
+#[test]
+fn test_render_schematic() {
+    let engine_schematic = vec![
+        "467..114".to_string().into_bytes(),
+        "...*....".to_string().into_bytes(),
+        "..35....".to_string().into_bytes(),
+    ];
+    let (part_numbers, symbols, index) = extract_part_numbers_and_gears(&engine_schematic);
+    let gears = symbols_adjacent_to_n_parts(b'*', 2, &part_numbers, &symbols, &index);
+
+    assert_eq!(
+        render_schematic(&engine_schematic, &part_numbers, &gears, false),
+        "467..114\n...*....\n..35....\n"
+    );
+
+    let rendered = render_schematic(&engine_schematic, &part_numbers, &gears, true);
+    let lines = rendered.lines().collect::<Vec<&str>>();
+    assert_eq!(
+        lines[0],
+        "\x1b[32m4\x1b[0m\x1b[32m6\x1b[0m\x1b[32m7\x1b[0m..\
+         \x1b[31m1\x1b[0m\x1b[31m1\x1b[0m\x1b[31m4\x1b[0m"
+    );
+    assert_eq!(lines[1], "...\x1b[1;33m*\x1b[0m....");
+}
@@ -1,60 +1,720 @@
-use std::{collections::HashSet, fs::read_to_string};
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::read_to_string,
+    io::{Error, ErrorKind},
+    str::FromStr,
+};
 
-use crate::utils::get_input_file_name;
+#[cfg(test)]
+use crate::utils::Rng;
+use crate::utils::{checked_add, flag_value, get_input_file_name, has_flag, Int};
 
-fn card_matches(card: &str) -> usize {
-    let numbers_str = card.split(':').collect::<Vec<&str>>()[1].trim();
-    let numbers_parts = numbers_str.split('|').collect::<Vec<&str>>();
-    let my_numbers = numbers_parts[1]
-        .trim()
-        .split(' ')
-        .filter_map(|n| n.parse::<i64>().ok())
-        .collect::<HashSet<i64>>();
+// Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+#[derive(Debug, PartialEq)]
+struct Scratchcard {
+    id: u64,
+    winning_numbers: Vec<u64>,
+    numbers: Vec<u64>,
+}
 
-    numbers_parts[0]
-        .trim()
-        .split(' ')
-        .filter_map(|n| n.parse::<i64>().ok())
-        .filter(|n| my_numbers.contains(n))
-        .collect::<HashSet<i64>>()
-        .len()
+#[derive(Debug, PartialEq)]
+enum ScratchcardError {
+    MissingHeader,
+    InvalidId(String),
+    MissingSeparator,
+    InvalidNumber(String),
+    DuplicateNumber(u64),
 }
 
-pub fn solve() {
-    let input_file_name = get_input_file_name(module_path!());
-    let mut cards: Vec<Card> = read_to_string(format!("src/{}", input_file_name))
-        .unwrap()
-        .lines()
-        .map(|line| {
-            let matches = card_matches(line);
-            let points = (0..matches).fold(0, |acc, _| if acc == 0 { 1 } else { acc * 2 });
-            Card {
-                num: 1,
-                points,
-                matches,
+impl fmt::Display for ScratchcardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScratchcardError::MissingHeader => write!(f, "missing `Card <id>:` header"),
+            ScratchcardError::InvalidId(id) => write!(f, "invalid card id {:?}", id),
+            ScratchcardError::MissingSeparator => write!(f, "missing `|` separator"),
+            ScratchcardError::InvalidNumber(n) => write!(f, "invalid number {:?}", n),
+            ScratchcardError::DuplicateNumber(n) => write!(f, "number {} listed twice", n),
+        }
+    }
+}
+
+impl std::error::Error for ScratchcardError {}
+
+fn parse_numbers(numbers: &str) -> Result<Vec<u64>, ScratchcardError> {
+    let mut seen = HashSet::new();
+    numbers
+        .split_whitespace()
+        .map(|n| {
+            let n = n
+                .parse::<u64>()
+                .map_err(|_| ScratchcardError::InvalidNumber(n.to_string()))?;
+            if !seen.insert(n) {
+                return Err(ScratchcardError::DuplicateNumber(n));
             }
+            Ok(n)
         })
-        .collect::<Vec<Card>>();
+        .collect()
+}
 
-    let points: i64 = cards.iter().map(|c| c.points).sum();
-    println!("module: {}, part 1, result: {}", module_path!(), points);
+impl FromStr for Scratchcard {
+    type Err = ScratchcardError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (header, numbers_str) = line
+            .split_once(':')
+            .ok_or(ScratchcardError::MissingHeader)?;
+        let id = header
+            .trim()
+            .strip_prefix("Card")
+            .ok_or(ScratchcardError::MissingHeader)?
+            .trim();
+        let id = id
+            .parse::<u64>()
+            .map_err(|_| ScratchcardError::InvalidId(id.to_string()))?;
+        let (winning_numbers, numbers) = numbers_str
+            .split_once('|')
+            .ok_or(ScratchcardError::MissingSeparator)?;
+        Ok(Scratchcard {
+            id,
+            winning_numbers: parse_numbers(winning_numbers)?,
+            numbers: parse_numbers(numbers)?,
+        })
+    }
+}
+
+impl Scratchcard {
+    fn matches(&self) -> usize {
+        let winning_numbers = self.winning_numbers.iter().collect::<HashSet<&u64>>();
+        self.numbers
+            .iter()
+            .filter(|n| winning_numbers.contains(n))
+            .count()
+    }
+}
+
+// Card numbers are small, so both lists of a card fit in a u128 bitset and matching needs no
+// allocation. Returns None for anything the fast path does not handle: numbers of 128 or more,
+// duplicates or malformed lines, which are left to the `Scratchcard` parser.
+fn card_matches_bitset(line: &[u8]) -> Option<usize> {
+    let rest = line.strip_prefix(b"Card")?;
+    let colon = rest.iter().position(|b| *b == b':')?;
+    // exactly `Card`, spaces and one run of digits short enough to always fit the u64 id
+    let id = &rest[rest.iter().position(|b| *b != b' ')?..colon];
+    if id.len() == colon || id.is_empty() || id.len() > 19 || !id.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    let mut sets = [0u128; 2];
+    let mut side = 0;
+    let mut number: Option<u32> = None;
+    // the trailing space flushes the last number
+    for byte in rest[colon + 1..].iter().chain(b" ") {
+        match byte {
+            b'0'..=b'9' => {
+                let n = number.unwrap_or(0) * 10 + (byte - b'0') as u32;
+                if n >= 128 {
+                    return None;
+                }
+                number = Some(n);
+            }
+            b' ' | b'\t' | b'|' => {
+                if let Some(n) = number.take() {
+                    let bit = 1u128 << n;
+                    if sets[side] & bit != 0 {
+                        return None;
+                    }
+                    sets[side] |= bit;
+                }
+                if *byte == b'|' {
+                    if side == 1 {
+                        return None;
+                    }
+                    side = 1;
+                }
+            }
+            _ => return None,
+        }
+    }
+    if side == 0 {
+        return None;
+    }
+    Some((sets[0] & sets[1]).count_ones() as usize)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ScoringRule {
+    // 1 point for the first match, doubled for every match after it
+    DoublingPoints,
+    // 1 point per match
+    LinearPoints,
+    // Each copy of a card wins one copy of each of the next `matches` cards. `cap` limits how
+    // many cards one card can win and `wrap_around` continues from the first card instead of
+    // stopping at the end of the table.
+    CascadeCopies {
+        cap: Option<usize>,
+        wrap_around: bool,
+    },
+}
+
+impl FromStr for ScoringRule {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        match rule {
+            "doubling" => Ok(ScoringRule::DoublingPoints),
+            "linear" => Ok(ScoringRule::LinearPoints),
+            "cascade" => Ok(ScoringRule::CascadeCopies {
+                cap: None,
+                wrap_around: false,
+            }),
+            _ => Err(format!("unknown scoring rule {:?}", rule)),
+        }
+    }
+}
+
+impl ScoringRule {
+    // `None` when the points do not fit in an i64, from 64 matches on when doubling.
+    fn card_points(&self, matches: usize) -> Option<i64> {
+        match self {
+            ScoringRule::DoublingPoints => match matches {
+                0 => Some(0),
+                _ => 2i64.checked_pow(u32::try_from(matches - 1).ok()?),
+            },
+            ScoringRule::LinearPoints => i64::try_from(matches).ok(),
+            ScoringRule::CascadeCopies { .. } => Some(0),
+        }
+    }
+
+    // Points rules sum every card's points; the cascade sums the cards held once every copy has
+    // been won. The cards are left as they are, so any rule can score them again. Returns the
+    // card whose points or count overflowed.
+    fn score(&self, cards: &[Card]) -> Result<Int, usize> {
+        match self {
+            ScoringRule::DoublingPoints | ScoringRule::LinearPoints => cards
+                .iter()
+                .enumerate()
+                .try_fold(Int::from(0), |acc, (i, card)| {
+                    let points = self.card_points(card.matches).ok_or(i)?;
+                    checked_add(&acc, &Int::from(points)).ok_or(i)
+                }),
+            ScoringRule::CascadeCopies { cap, wrap_around } => {
+                cascade_copies(cards, *cap, *wrap_around)?
+                    .iter()
+                    .enumerate()
+                    .try_fold(Int::from(0), |acc, (i, count)| {
+                        checked_add(&acc, count).ok_or(i)
+                    })
+            }
+        }
+    }
+}
 
+// The cards won by card `i`. A card never wins a copy of itself, so wrapping around stops one
+// short of it.
+fn won_cards(
+    i: usize,
+    matches: usize,
+    len: usize,
+    cap: Option<usize>,
+    wrap_around: bool,
+) -> impl Iterator<Item = usize> {
+    let count = cap.map_or(matches, |cap| matches.min(cap));
+    let count = if wrap_around {
+        count.min(len - 1)
+    } else {
+        count.min(len - 1 - i)
+    };
+    (1..=count).map(move |k| (i + k) % len)
+}
+
+// Cards are played once, in order. Copies won of a card that was already played, which only
+// happens when wrapping around, are counted but do not win anything themselves. Returns how many
+// of each card are held at the end, or the card whose count overflowed.
+fn cascade_copies(
+    cards: &[Card],
+    cap: Option<usize>,
+    wrap_around: bool,
+) -> Result<Vec<Int>, usize> {
+    let mut counts = cards.iter().map(|c| c.num.to_owned()).collect::<Vec<Int>>();
     for i in 0..cards.len() {
-        for j in i + 1..=i + cards[i].matches {
-            cards[j].num += cards[i].num;
+        for j in won_cards(i, cards[i].matches, cards.len(), cap, wrap_around) {
+            counts[j] = checked_add(&counts[j], &counts[i]).ok_or(j)?;
         }
     }
-    let total_cards: i64 = cards.iter().map(|c| c.num).sum();
+    Ok(counts)
+}
+
+// How one card's count came about during the cascade.
+#[derive(Debug, PartialEq)]
+struct CardTrace {
+    card: usize,
+    original: Int,
+    copies_won: Int,
+    // earlier cards that won copies of this one, with how many copies each gave
+    contributors: Vec<(usize, Int)>,
+    running_total: Int,
+    copies_generated: Int,
+}
+
+// Runs the default cascade, recording where every copy came from. Card numbers
+// in the trace are 1-based.
+fn trace_cascade(cards: &mut [Card]) -> Result<Vec<CardTrace>, usize> {
+    let mut traces = (0..cards.len())
+        .map(|i| CardTrace {
+            card: i + 1,
+            original: cards[i].num.to_owned(),
+            copies_won: Int::from(0),
+            contributors: vec![],
+            running_total: Int::from(0),
+            copies_generated: Int::from(0),
+        })
+        .collect::<Vec<CardTrace>>();
+    let mut running_total = Int::from(0);
+    for i in 0..cards.len() {
+        for j in won_cards(i, cards[i].matches, cards.len(), None, false) {
+            cards[j].num = checked_add(&cards[j].num, &cards[i].num).ok_or(j)?;
+            traces[j].copies_won += &cards[i].num;
+            traces[j]
+                .contributors
+                .push((i + 1, cards[i].num.to_owned()));
+            traces[i].copies_generated =
+                checked_add(&traces[i].copies_generated, &cards[i].num).ok_or(i)?;
+        }
+        running_total = checked_add(&running_total, &cards[i].num).ok_or(i)?;
+        traces[i].running_total = running_total.to_owned();
+    }
+    Ok(traces)
+}
+
+// The first card that handed out the most copies.
+fn most_generous_card(traces: &[CardTrace]) -> Option<&CardTrace> {
+    traces
+        .iter()
+        .fold(None, |best: Option<&CardTrace>, trace| match best {
+            Some(best) if best.copies_generated >= trace.copies_generated => Some(best),
+            _ => Some(trace),
+        })
+}
+
+fn cascade_report_text(traces: &[CardTrace]) -> String {
+    let mut res = String::new();
+    for trace in traces {
+        let contributors = trace
+            .contributors
+            .iter()
+            .map(|(card, copies)| format!("card {} x{}", card, copies))
+            .collect::<Vec<String>>()
+            .join(", ");
+        res.push_str(&format!(
+            "card {}: {} original + {} won = {}",
+            trace.card,
+            trace.original,
+            trace.copies_won,
+            trace.original.to_owned() + trace.copies_won.to_owned()
+        ));
+        if !contributors.is_empty() {
+            res.push_str(&format!(" (from {})", contributors));
+        }
+        res.push_str(&format!(
+            ", running total {}, generated {}\n",
+            trace.running_total, trace.copies_generated
+        ));
+    }
+    if let Some(best) = most_generous_card(traces) {
+        res.push_str(&format!(
+            "card {} generated the most copies: {}\n",
+            best.card, best.copies_generated
+        ));
+    }
+    res
+}
+
+fn cascade_report_json(traces: &[CardTrace]) -> String {
+    let cards = traces
+        .iter()
+        .map(|trace| {
+            let contributors = trace
+                .contributors
+                .iter()
+                .map(|(card, copies)| format!("{{\"card\":{},\"copies\":{}}}", card, copies))
+                .collect::<Vec<String>>()
+                .join(",");
+            format!(
+                "{{\"card\":{},\"original\":{},\"copies_won\":{},\"contributors\":[{}],\
+                 \"running_total\":{},\"copies_generated\":{}}}",
+                trace.card,
+                trace.original,
+                trace.copies_won,
+                contributors,
+                trace.running_total,
+                trace.copies_generated
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    let total = traces
+        .last()
+        .map_or(Int::from(0), |t| t.running_total.to_owned());
+    let most_copies = match most_generous_card(traces) {
+        Some(best) => format!(
+            "{{\"card\":{},\"copies_generated\":{}}}",
+            best.card, best.copies_generated
+        ),
+        None => "null".to_string(),
+    };
+    format!(
+        "{{\"cards\":[{}],\"total\":{},\"most_copies\":{}}}\n",
+        cards, total, most_copies
+    )
+}
+
+fn parse_cards(contents: &str) -> Result<Vec<Card>, Error> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let matches = match card_matches_bitset(line.as_bytes()) {
+                Some(matches) => matches,
+                None => line
+                    .parse::<Scratchcard>()
+                    .map_err(|e| {
+                        Error::new(ErrorKind::InvalidData, format!("line {}: {}", i + 1, e))
+                    })?
+                    .matches(),
+            };
+            Ok(Card {
+                num: Int::from(1),
+                matches,
+            })
+        })
+        .collect()
+}
+
+fn overflow_error(card: usize) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("score of card {} overflows", card + 1),
+    )
+}
+
+pub fn solve() -> Result<(), Error> {
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    let cards = parse_cards(&contents)?;
+
+    let points = ScoringRule::DoublingPoints
+        .score(&cards)
+        .map_err(overflow_error)?;
+    println!("module: {}, part 1, result: {}", module_path!(), points);
+
+    let total_cards = ScoringRule::CascadeCopies {
+        cap: None,
+        wrap_around: false,
+    }
+    .score(&cards)
+    .map_err(overflow_error)?;
     println!(
         "module: {}, part 2, result: {}",
         module_path!(),
         total_cards
     );
+    Ok(())
+}
+
+pub fn cascade_report(args: &[String]) -> Result<(), Error> {
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    let mut cards = parse_cards(&contents)?;
+    let traces = trace_cascade(&mut cards).map_err(overflow_error)?;
+    if has_flag(args, "--json") {
+        print!("{}", cascade_report_json(&traces));
+    } else {
+        print!("{}", cascade_report_text(&traces));
+    }
+    Ok(())
+}
+
+// Scores the cards with one rule, e.g. `score 4 --rule cascade --cap 2 --wrap`.
+pub fn score(args: &[String]) -> Result<(), Error> {
+    let invalid_input = |e: String| Error::new(ErrorKind::InvalidInput, e);
+    let mut rule = flag_value(args, "--rule")
+        .unwrap_or("doubling")
+        .parse::<ScoringRule>()
+        .map_err(invalid_input)?;
+    if let ScoringRule::CascadeCopies { cap, wrap_around } = &mut rule {
+        *cap = flag_value(args, "--cap")
+            .map(|n| n.parse::<usize>().map_err(|e| invalid_input(e.to_string())))
+            .transpose()?;
+        *wrap_around = has_flag(args, "--wrap");
+    }
+
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    let cards = parse_cards(&contents)?;
+    let score = rule.score(&cards).map_err(overflow_error)?;
+    println!("module: {}, {:?}, result: {}", module_path!(), rule, score);
+    Ok(())
 }
 
 #[derive(Debug)]
 struct Card {
-    num: i64,
-    points: i64,
+    num: Int,
     matches: usize,
 }
+
+#[cfg(test)]
+const EXAMPLE: &str = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
+#[test]
+fn test_parse_scratchcard() {
+    let card = "Card   3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1"
+        .parse::<Scratchcard>()
+        .unwrap();
+    assert_eq!(
+        card,
+        Scratchcard {
+            id: 3,
+            winning_numbers: vec![1, 21, 53, 59, 44],
+            numbers: vec![69, 82, 63, 72, 16, 21, 14, 1],
+        }
+    );
+    assert_eq!(card.matches(), 2);
+
+    let cases = [
+        ("41 48 | 83 86", ScratchcardError::MissingHeader),
+        ("Game 1: 41 48 | 83 86", ScratchcardError::MissingHeader),
+        (
+            "Card x: 41 48 | 83 86",
+            ScratchcardError::InvalidId("x".to_string()),
+        ),
+        ("Card 1: 41 48 83 86", ScratchcardError::MissingSeparator),
+        (
+            "Card 1: 41 4a | 83 86",
+            ScratchcardError::InvalidNumber("4a".to_string()),
+        ),
+        (
+            "Card 1: 41 48 | 83 86 83",
+            ScratchcardError::DuplicateNumber(83),
+        ),
+    ];
+    for (line, error) in cases {
+        assert_eq!(line.parse::<Scratchcard>(), Err(error));
+    }
+}
+
+#[test]
+fn test_cascade_copies() {
+    let cards = parse_cards(EXAMPLE).unwrap();
+    assert_eq!(
+        cascade_copies(&cards, None, false),
+        Ok(vec![1, 2, 4, 8, 14, 1].into_iter().map(Int::from).collect())
+    );
+}
+
+#[test]
+fn test_cascade_copies_past_the_last_card() {
+    let cards = parse_cards("Card 1: 1 2 3 | 1 2 3\nCard 2: 4 5 | 4 5").unwrap();
+    assert_eq!(
+        cascade_copies(&cards, None, false),
+        Ok(vec![Int::from(1), Int::from(2)])
+    );
+
+    let cards = vec![
+        Card {
+            num: Int::from(i64::MAX),
+            matches: 1,
+        },
+        Card {
+            num: Int::from(1),
+            matches: 0,
+        },
+    ];
+    // with arbitrary-precision counts there is nothing to overflow
+    let expected = if cfg!(feature = "bigint") {
+        Ok(Int::from(i64::MAX))
+    } else {
+        Err(1)
+    };
+    assert_eq!(
+        cascade_copies(&cards, None, false).map(|counts| counts[0].to_owned()),
+        expected
+    );
+}
+
+#[test]
+fn test_trace_cascade() {
+    let mut cards = parse_cards(EXAMPLE).unwrap();
+    let traces = trace_cascade(&mut cards).unwrap();
+    assert_eq!(
+        traces[3],
+        CardTrace {
+            card: 4,
+            original: Int::from(1),
+            copies_won: Int::from(7),
+            contributors: vec![(1, 1), (2, 2), (3, 4)]
+                .into_iter()
+                .map(|(card, copies)| (card, Int::from(copies)))
+                .collect(),
+            running_total: Int::from(15),
+            copies_generated: Int::from(8),
+        }
+    );
+    assert_eq!(traces.last().unwrap().running_total, Int::from(30));
+    assert_eq!(most_generous_card(&traces).unwrap().card, 3);
+
+    let text = cascade_report_text(&traces);
+    assert_eq!(
+        text.lines().nth(3),
+        Some(
+            "card 4: 1 original + 7 won = 8 (from card 1 x1, card 2 x2, card 3 x4), \
+             running total 15, generated 8"
+        )
+    );
+    assert_eq!(
+        text.lines().last(),
+        Some("card 3 generated the most copies: 8")
+    );
+
+    let json = cascade_report_json(&traces[..2]);
+    assert_eq!(
+        json,
+        "{\"cards\":[\
+         {\"card\":1,\"original\":1,\"copies_won\":0,\"contributors\":[],\
+         \"running_total\":1,\"copies_generated\":4},\
+         {\"card\":2,\"original\":1,\"copies_won\":1,\"contributors\":[{\"card\":1,\"copies\":1}],\
+         \"running_total\":3,\"copies_generated\":4}\
+         ],\"total\":3,\"most_copies\":{\"card\":1,\"copies_generated\":4}}\n"
+    );
+}
+
+#[test]
+fn test_scoring_rules() {
+    let cases = [
+        (ScoringRule::DoublingPoints, 13),
+        (ScoringRule::LinearPoints, 9),
+        (
+            ScoringRule::CascadeCopies {
+                cap: None,
+                wrap_around: false,
+            },
+            30,
+        ),
+        // cards 1 to 4 only win the next card: 1, 2, 3, 4, 5, 1
+        (
+            ScoringRule::CascadeCopies {
+                cap: Some(1),
+                wrap_around: false,
+            },
+            16,
+        ),
+    ];
+    let cards = parse_cards(EXAMPLE).unwrap();
+    for (rule, score) in cases {
+        assert_eq!(rule.score(&cards), Ok(Int::from(score)));
+        // scoring leaves the cards as they were, so a second run agrees
+        assert_eq!(rule.score(&cards), Ok(Int::from(score)));
+    }
+}
+
+#[test]
+fn test_doubling_points_overflow() {
+    assert_eq!(ScoringRule::DoublingPoints.card_points(63), Some(1 << 62));
+    assert_eq!(ScoringRule::DoublingPoints.card_points(64), None);
+
+    // 64 matches only overflow the doubling rule, the cascade never looks at points
+    let numbers = (0..64)
+        .map(|n| n.to_string())
+        .collect::<Vec<String>>()
+        .join(" ");
+    let contents = format!("Card 1: {} | {}\nCard 2: 1 | 2", numbers, numbers);
+    let cards = parse_cards(&contents).unwrap();
+    assert_eq!(ScoringRule::DoublingPoints.score(&cards), Err(0));
+    assert_eq!(ScoringRule::LinearPoints.score(&cards), Ok(Int::from(64)));
+    let cascade = ScoringRule::CascadeCopies {
+        cap: None,
+        wrap_around: false,
+    };
+    assert_eq!(cascade.score(&cards), Ok(Int::from(3)));
+}
+
+#[test]
+fn test_cascade_copies_wrap_around() {
+    let cards = parse_cards("Card 1: 1 | 2\nCard 2: 1 | 1\nCard 3: 1 2 | 1 2").unwrap();
+    // card 2 wins one copy of card 3, then card 3's two copies wrap around to cards 1 and 2
+    assert_eq!(
+        cascade_copies(&cards, None, true),
+        Ok(vec![3, 3, 2].into_iter().map(Int::from).collect())
+    );
+
+    // a card can not win a copy of itself however many matches it has
+    let cards = parse_cards("Card 1: 1 2 3 | 1 2 3\nCard 2: 4 | 5").unwrap();
+    assert_eq!(
+        cascade_copies(&cards, None, true),
+        Ok(vec![Int::from(1), Int::from(2)])
+    );
+}
+
+#[test]
+fn test_card_matches_bitset() {
+    let matches = EXAMPLE
+        .lines()
+        .map(|line| card_matches_bitset(line.as_bytes()))
+        .collect::<Vec<Option<usize>>>();
+    assert_eq!(
+        matches,
+        vec![Some(4), Some(2), Some(2), Some(1), Some(0), Some(0)]
+    );
+
+    let fallbacks = [
+        "Card 1: 41 48 | 83 128",
+        "Card 1: 41 48 | 83 83",
+        "Card 1: 41 48 83 86",
+        "Card 1: 41 48 | 83 | 86",
+        "Card : 41 48 | 83 86",
+        "Card 1 2: 41 48 | 83 86",
+        "Card 1 : 41 48 | 83 86",
+        "Card1: 41 48 | 83 86",
+        "Card x: 41 48 | 83 86",
+        "Card 99999999999999999999: 41 48 | 83 86",
+        "Card 1: 4a 48 | 83 86",
+        "41 48 | 83 86",
+    ];
+    for line in fallbacks {
+        assert_eq!(card_matches_bitset(line.as_bytes()), None, "{}", line);
+    }
+}
+
+#[test]
+fn test_card_matches_bitset_against_scratchcard() {
+    let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+    for id in 1..=2000 {
+        let mut numbers = (0..100).collect::<Vec<u64>>();
+        // partial Fisher-Yates shuffle for 10 winning numbers and 25 of our own, allowing the
+        // two lists to share numbers
+        let mut pick = |count: usize| {
+            (0..count)
+                .map(|i| {
+                    let j = i + rng.below(numbers.len() - i);
+                    numbers.swap(i, j);
+                    format!("{:>2}", numbers[i])
+                })
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let winning = pick(10);
+        let mine = pick(25);
+        let line = format!("Card {:>4}: {} | {}", id, winning, mine);
+        assert_eq!(
+            card_matches_bitset(line.as_bytes()),
+            Some(line.parse::<Scratchcard>().unwrap().matches()),
+            "{}",
+            line
+        );
+    }
+}
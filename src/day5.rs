@@ -1,6 +1,12 @@
-use std::{collections::HashMap, fs::read_to_string, io::Error};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{read_to_string, write},
+    io::{Error, ErrorKind},
+    ops::Range,
+};
 
-use crate::utils::get_input_file_name;
+use crate::utils::{flag_value, get_input_file_name};
 
 // seeds: 79 14 55 13
 
@@ -36,66 +42,273 @@ use crate::utils::get_input_file_name;
 // 60 56 37
 // 56 93 4
 
-fn parse_seeds(line: &str) -> Vec<i64> {
-    line.split(": ")
-        .last()
-        .unwrap()
-        .split(' ')
-        .map(|num| num.to_string().trim().parse::<i64>().unwrap())
-        .collect::<Vec<i64>>()
-}
-
-fn parse_mapping(chunk: &str) -> (&str, &str, Vec<Entry>) {
-    let source_to_dist = chunk
-        .lines()
-        .next()
-        .unwrap()
-        .split(' ')
-        .next()
-        .unwrap()
-        .split('-')
-        .collect::<Vec<&str>>();
-    let source = source_to_dist[0];
-    let dist = source_to_dist[2];
-
-    let entries = chunk
-        .lines()
-        .skip(1)
-        .map(|line| {
-            let nums = line
-                .split(' ')
-                .map(|n| n.to_string().parse::<i64>().unwrap())
-                .collect::<Vec<i64>>();
-            Entry {
-                source: nums[1],
-                dist: nums[0],
-                range: nums[2],
+// A problem found in a hand-edited almanac. Lines are 1-based.
+#[derive(Debug, PartialEq)]
+enum AlmanacIssue {
+    Malformed { line: usize, reason: String },
+    DuplicateHeader { line: usize, first_line: usize },
+    NonPositiveRange { line: usize, range: i64 },
+    Overflow { line: usize },
+    // the source ranges of two entries of the same map share values
+    Overlap { line: usize, other_line: usize },
+}
+
+impl fmt::Display for AlmanacIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlmanacIssue::Malformed { line, reason } => write!(f, "line {}: {}", line, reason),
+            AlmanacIssue::DuplicateHeader { line, first_line } => write!(
+                f,
+                "line {}: source category already mapped on line {}",
+                line, first_line
+            ),
+            AlmanacIssue::NonPositiveRange { line, range } => {
+                write!(f, "line {}: range length {} is not positive", line, range)
             }
-        })
-        .collect::<Vec<Entry>>();
-    (source, dist, entries)
+            AlmanacIssue::Overflow { line } => {
+                write!(f, "line {}: range end does not fit in an i64", line)
+            }
+            AlmanacIssue::Overlap { line, other_line } => write!(
+                f,
+                "line {}: source range overlaps the entry on line {}",
+                line, other_line
+            ),
+        }
+    }
+}
+
+// Reads the almanac a line at a time, collecting every problem a hand-edited file can have
+// instead of stopping at the first one. `check-input` reports them and `parse_almanac` refuses to
+// hand on the mappings when there are any, so the solver never sees an entry that makes
+// `find_map` depend on entry order or an addition that overflows.
+fn read_almanac(contents: &str) -> (Vec<i64>, Mappings<'_>, Vec<AlmanacIssue>) {
+    let mut issues = vec![];
+    let mut seeds = vec![];
+    let mut mappings: Mappings<'_> = HashMap::new();
+    let mut lines = contents.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    match lines.next() {
+        Some((line, text)) => {
+            let nums = text.strip_prefix("seeds:").map(|nums| {
+                nums.split_whitespace()
+                    .map(|n| n.parse::<i64>())
+                    .collect::<Result<Vec<i64>, _>>()
+            });
+            match nums {
+                Some(Ok(nums)) => {
+                    // part 2 reads the seeds as `<start> <length>` pairs
+                    if nums.len() % 2 == 1 {
+                        issues.push(AlmanacIssue::Malformed {
+                            line,
+                            reason: "expected seed ranges as `<start> <length>` pairs".to_string(),
+                        });
+                    }
+                    if nums
+                        .chunks_exact(2)
+                        .any(|pair| pair[0].checked_add(pair[1]).is_none())
+                    {
+                        issues.push(AlmanacIssue::Overflow { line });
+                    }
+                    seeds = nums;
+                }
+                _ => issues.push(AlmanacIssue::Malformed {
+                    line,
+                    reason: "expected `seeds: <numbers>`".to_string(),
+                }),
+            }
+        }
+        None => issues.push(AlmanacIssue::Malformed {
+            line: 1,
+            reason: "empty almanac".to_string(),
+        }),
+    }
+
+    let mut headers: HashMap<&str, usize> = HashMap::new();
+    // (line, source range) of every entry in the current map
+    let mut entries: Vec<(usize, Range<i64>)> = vec![];
+    let mut in_map = false;
+    // the map entries go to, `None` under a malformed or repeated header
+    let mut current: Option<(&str, &str)> = None;
+    let check_overlaps = |entries: &mut Vec<(usize, Range<i64>)>,
+                          issues: &mut Vec<AlmanacIssue>| {
+        entries.sort_by_key(|(line, range)| (range.start, *line));
+        // the entry reaching furthest so far, which every later overlap has to touch
+        let mut furthest: Option<&(usize, Range<i64>)> = None;
+        for entry in entries.iter() {
+            if let Some((other_line, other)) = furthest {
+                if entry.1.start < other.end {
+                    issues.push(AlmanacIssue::Overlap {
+                        line: entry.0.max(*other_line),
+                        other_line: entry.0.min(*other_line),
+                    });
+                }
+            }
+            if furthest.is_none_or(|(_, other)| entry.1.end > other.end) {
+                furthest = Some(entry);
+            }
+        }
+        entries.clear();
+    };
+
+    for (line, text) in lines {
+        let text = text.trim();
+        if text.is_empty() {
+            check_overlaps(&mut entries, &mut issues);
+            in_map = false;
+            continue;
+        }
+        if let Some(header) = text.strip_suffix(" map:") {
+            check_overlaps(&mut entries, &mut issues);
+            in_map = true;
+            current = None;
+            match header.split('-').collect::<Vec<&str>>()[..] {
+                [src, "to", dist] => {
+                    if let Some(first_line) = headers.insert(src, line) {
+                        issues.push(AlmanacIssue::DuplicateHeader { line, first_line });
+                        headers.insert(src, first_line);
+                    } else {
+                        current = Some((src, dist));
+                        mappings.insert((src, dist), vec![]);
+                    }
+                }
+                _ => issues.push(AlmanacIssue::Malformed {
+                    line,
+                    reason: format!("expected `<source>-to-<destination> map:`, got {:?}", text),
+                }),
+            }
+            continue;
+        }
+        if !in_map {
+            issues.push(AlmanacIssue::Malformed {
+                line,
+                reason: "entry outside of a map".to_string(),
+            });
+            continue;
+        }
+        let nums = text
+            .split_whitespace()
+            .map(|n| n.parse::<i64>())
+            .collect::<Result<Vec<i64>, _>>();
+        let (dist, source, range) = match nums.as_deref() {
+            Ok([dist, source, range]) => (*dist, *source, *range),
+            _ => {
+                issues.push(AlmanacIssue::Malformed {
+                    line,
+                    reason: "expected `<destination> <source> <length>`".to_string(),
+                });
+                continue;
+            }
+        };
+        if range <= 0 {
+            issues.push(AlmanacIssue::NonPositiveRange { line, range });
+            continue;
+        }
+        match (source.checked_add(range), dist.checked_add(range)) {
+            (Some(end), Some(_)) => {
+                entries.push((line, source..end));
+                if let Some(key) = current {
+                    mappings.entry(key).or_default().push(Entry {
+                        source,
+                        dist,
+                        range,
+                    });
+                }
+            }
+            _ => issues.push(AlmanacIssue::Overflow { line }),
+        }
+    }
+    check_overlaps(&mut entries, &mut issues);
+    (seeds, mappings, issues)
 }
 
 fn traverse_mappings(
     starting_src: &str,
     starting_value: i64,
     mappings: &HashMap<(&str, &str), Vec<Entry>>,
+) -> i64 {
+    walk_mappings(starting_src, starting_value, mappings, |_, _, _| {})
+}
+
+// Follows the maps from `starting_src` to the end of the chain, calling `visit` with every
+// category reached, its value and the entry that produced it, if any.
+fn walk_mappings<'a>(
+    starting_src: &'a str,
+    starting_value: i64,
+    mappings: &'a HashMap<(&'a str, &'a str), Vec<Entry>>,
+    mut visit: impl FnMut(&'a str, i64, Option<&'a Entry>),
 ) -> i64 {
     let mut result = starting_value;
     let mut current_src = starting_src;
     while let Some(((_, dist), entries)) = mappings.iter().find(|((src, _), _)| src == &current_src)
     {
-        if let Some(value) = entries
+        let matched = entries
             .iter()
-            .find_map(|entry| entry.get_dist_value(result))
-        {
+            .find_map(|entry| entry.get_dist_value(result).map(|value| (value, entry)));
+        if let Some((value, _)) = matched {
             result = value;
         }
+        visit(dist, result, matched.map(|(_, entry)| entry));
         current_src = dist;
     }
     result
 }
 
+#[derive(Debug, PartialEq)]
+enum TraceSource<'a> {
+    Start,
+    Entry(&'a Entry),
+    Unchanged,
+}
+
+#[derive(Debug, PartialEq)]
+struct TraceStep<'a> {
+    category: &'a str,
+    value: i64,
+    source: TraceSource<'a>,
+}
+
+// Every category a value passes through on its way down the chain of maps.
+fn trace_mappings<'a>(
+    starting_src: &'a str,
+    starting_value: i64,
+    mappings: &'a HashMap<(&'a str, &'a str), Vec<Entry>>,
+) -> Vec<TraceStep<'a>> {
+    let mut steps = vec![TraceStep {
+        category: starting_src,
+        value: starting_value,
+        source: TraceSource::Start,
+    }];
+    walk_mappings(
+        starting_src,
+        starting_value,
+        mappings,
+        |category, value, entry| {
+            steps.push(TraceStep {
+                category,
+                value,
+                source: entry.map_or(TraceSource::Unchanged, TraceSource::Entry),
+            })
+        },
+    );
+    steps
+}
+
+// seed 79 → soil 81 (entry 52 50 48) → fertilizer 81 (unchanged) → ...
+fn format_trace(steps: &[TraceStep]) -> String {
+    steps
+        .iter()
+        .map(|step| match step.source {
+            TraceSource::Start => format!("{} {}", step.category, step.value),
+            TraceSource::Entry(entry) => format!(
+                "{} {} (entry {} {} {})",
+                step.category, step.value, entry.dist, entry.source, entry.range
+            ),
+            TraceSource::Unchanged => format!("{} {} (unchanged)", step.category, step.value),
+        })
+        .collect::<Vec<String>>()
+        .join(" → ")
+}
+
 fn reverse_traverse_mappings(
     starting_dist: &str,
     starting_value: i64,
@@ -114,6 +327,260 @@ fn reverse_traverse_mappings(
     result
 }
 
+#[derive(Debug, PartialEq)]
+enum AlmanacError {
+    UnknownCategory(String),
+    NotConnected { from: String, to: String },
+}
+
+impl fmt::Display for AlmanacError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlmanacError::UnknownCategory(category) => {
+                write!(f, "unknown category {:?}", category)
+            }
+            AlmanacError::NotConnected { from, to } => {
+                write!(f, "no chain of maps connects {} and {}", from, to)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AlmanacError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Direction {
+    // source to destination, as the maps are written
+    Forward,
+    // destination back to source
+    Backward,
+}
+
+// The maps to apply, in order, to get from one category to another.
+fn category_path<'a>(
+    from: &str,
+    to: &str,
+    mappings: &'a HashMap<(&'a str, &'a str), Vec<Entry>>,
+) -> Result<(Direction, Vec<&'a Vec<Entry>>), AlmanacError> {
+    for category in [from, to] {
+        if !mappings
+            .keys()
+            .any(|(src, dist)| *src == category || *dist == category)
+        {
+            return Err(AlmanacError::UnknownCategory(category.to_string()));
+        }
+    }
+    let forward_path = |from: &str, to: &str| {
+        let mut path = vec![];
+        let mut current_src = from;
+        while current_src != to {
+            let ((_, dist), entries) = mappings.iter().find(|((src, _), _)| *src == current_src)?;
+            path.push(entries);
+            current_src = dist;
+            // a cycle in a hand-edited almanac would otherwise never end
+            if path.len() > mappings.len() {
+                return None;
+            }
+        }
+        Some(path)
+    };
+    if let Some(path) = forward_path(from, to) {
+        return Ok((Direction::Forward, path));
+    }
+    if let Some(mut path) = forward_path(to, from) {
+        path.reverse();
+        return Ok((Direction::Backward, path));
+    }
+    Err(AlmanacError::NotConnected {
+        from: from.to_string(),
+        to: to.to_string(),
+    })
+}
+
+fn map_value(
+    from: &str,
+    to: &str,
+    value: i64,
+    mappings: &HashMap<(&str, &str), Vec<Entry>>,
+) -> Result<i64, AlmanacError> {
+    let (direction, path) = category_path(from, to, mappings)?;
+    Ok(path.iter().fold(value, |value, entries| {
+        entries
+            .iter()
+            .find_map(|entry| match direction {
+                Direction::Forward => entry.get_dist_value(value),
+                Direction::Backward => entry.get_src_value(value),
+            })
+            .unwrap_or(value)
+    }))
+}
+
+// Splits every range wherever it crosses an entry boundary of one layer, pairing each piece with
+// where it maps to. Parts no entry covers pass through unchanged, and the first entry wins where
+// entries overlap, as in `traverse_mappings`.
+fn split_ranges_through(
+    ranges: Vec<Range<i64>>,
+    entries: &[Entry],
+    direction: Direction,
+) -> Vec<(Range<i64>, Range<i64>)> {
+    let mut mapped = vec![];
+    let mut unmapped = ranges;
+    for entry in entries {
+        // measured from the start of the entry, as the distance between the two ends of an entry
+        // need not fit in an i64
+        let (covered, target) = match direction {
+            Direction::Forward => (entry.source_range(), entry.dist_range()),
+            Direction::Backward => (entry.dist_range(), entry.source_range()),
+        };
+        let mut rest = vec![];
+        for range in unmapped {
+            let start = range.start.max(covered.start);
+            let end = range.end.min(covered.end);
+            if start >= end {
+                rest.push(range);
+                continue;
+            }
+            mapped.push((
+                start..end,
+                target.start + (start - covered.start)..target.start + (end - covered.start),
+            ));
+            if range.start < start {
+                rest.push(range.start..start);
+            }
+            if end < range.end {
+                rest.push(end..range.end);
+            }
+        }
+        unmapped = rest;
+    }
+    mapped.extend(unmapped.into_iter().map(|range| (range.clone(), range)));
+    mapped.sort_by_key(|(range, _)| range.start);
+    mapped
+}
+
+fn map_ranges_through(
+    ranges: Vec<Range<i64>>,
+    entries: &[Entry],
+    direction: Direction,
+) -> Vec<Range<i64>> {
+    let mut mapped = split_ranges_through(ranges, entries, direction)
+        .into_iter()
+        .map(|(_, range)| range)
+        .collect::<Vec<Range<i64>>>();
+    mapped.sort_by_key(|range| range.start);
+    mapped
+}
+
+fn map_range(
+    from: &str,
+    to: &str,
+    range: Range<i64>,
+    mappings: &HashMap<(&str, &str), Vec<Entry>>,
+) -> Result<Vec<Range<i64>>, AlmanacError> {
+    let (direction, path) = category_path(from, to, mappings)?;
+    Ok(path.iter().fold(vec![range], |ranges, entries| {
+        map_ranges_through(ranges, entries, direction)
+    }))
+}
+
+const SVG_LEFT: f64 = 120.;
+const SVG_WIDTH: f64 = 1000.;
+const SVG_TOP: f64 = 30.;
+const SVG_BAND_HEIGHT: f64 = 90.;
+
+// Draws every category as a horizontal axis, every map entry as a band from its source range to
+// its destination range, and the part 2 seed ranges flowing down and splitting at entry
+// boundaries. Overlapping entries show up as darker bands.
+fn render_svg(seeds: &[i64], mappings: &HashMap<(&str, &str), Vec<Entry>>) -> String {
+    let mut categories = vec!["seed"];
+    let mut layers = vec![];
+    while let Some(((_, dist), entries)) = mappings
+        .iter()
+        .find(|((src, _), _)| src == categories.last().unwrap())
+    {
+        if categories.contains(dist) {
+            break;
+        }
+        categories.push(dist);
+        layers.push(entries);
+    }
+    let seed_ranges = seeds
+        .chunks(2)
+        .filter(|chunk| chunk.len() == 2)
+        .map(|chunk| chunk[0]..chunk[0] + chunk[1])
+        .collect::<Vec<Range<i64>>>();
+
+    let max_value = layers
+        .iter()
+        .flat_map(|entries| entries.iter())
+        .flat_map(|entry| [entry.source_range().end, entry.dist_range().end])
+        .chain(seed_ranges.iter().map(|range| range.end))
+        .max()
+        .unwrap_or(1)
+        .max(1);
+    let x = |value: i64| SVG_LEFT + value as f64 / max_value as f64 * SVG_WIDTH;
+    let y = |level: usize| SVG_TOP + level as f64 * SVG_BAND_HEIGHT;
+    let polygon = |top: &Range<i64>, bottom: &Range<i64>, level: usize, style: &str| {
+        format!(
+            "<polygon points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" {}/>\n",
+            x(top.start),
+            y(level),
+            x(top.end),
+            y(level),
+            x(bottom.end),
+            y(level + 1),
+            x(bottom.start),
+            y(level + 1),
+            style
+        )
+    };
+
+    let height = y(categories.len() - 1) + SVG_TOP;
+    let width = SVG_LEFT + SVG_WIDTH + 20.;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\" font-family=\"monospace\" font-size=\"12\">\n",
+        w = width,
+        h = height
+    );
+    for (level, category) in categories.iter().enumerate() {
+        svg.push_str(&format!(
+            "<text x=\"10\" y=\"{:.1}\">{}</text>\n\
+             <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#333\"/>\n",
+            y(level) + 4.,
+            category,
+            x(0),
+            y(level),
+            x(max_value),
+            y(level)
+        ));
+    }
+
+    let mut ranges = seed_ranges;
+    for (level, entries) in layers.iter().enumerate() {
+        for entry in entries.iter() {
+            svg.push_str(&polygon(
+                &entry.source_range(),
+                &entry.dist_range(),
+                level,
+                "fill=\"#999\" fill-opacity=\"0.35\" stroke=\"#555\" stroke-width=\"0.5\"",
+            ));
+        }
+        let pieces = split_ranges_through(ranges, entries, Direction::Forward);
+        for (from, to) in pieces.iter() {
+            svg.push_str(&polygon(
+                from,
+                to,
+                level,
+                "fill=\"#1f77b4\" fill-opacity=\"0.6\"",
+            ));
+        }
+        ranges = pieces.into_iter().map(|(_, to)| to).collect();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
 fn solve_part_1(seeds: &[i64], mappings: &HashMap<(&str, &str), Vec<Entry>>) -> i64 {
     let mut location: i64 = i64::MAX;
     for seed in seeds.iter() {
@@ -138,21 +605,26 @@ fn solve_part_2(seeds: &[i64], mappings: &HashMap<(&str, &str), Vec<Entry>>) ->
     None
 }
 
+type Mappings<'a> = HashMap<(&'a str, &'a str), Vec<Entry>>;
+
+// The seeds and mappings, or every issue `read_almanac` found, one per line.
+fn parse_almanac(contents: &str) -> Result<(Vec<i64>, Mappings<'_>), Error> {
+    let (seeds, mappings, issues) = read_almanac(contents);
+    if issues.is_empty() {
+        return Ok((seeds, mappings));
+    }
+    let report = issues
+        .iter()
+        .map(AlmanacIssue::to_string)
+        .collect::<Vec<String>>()
+        .join("\n");
+    Err(Error::new(ErrorKind::InvalidData, report))
+}
+
 pub fn solve() -> Result<(), Error> {
     let input_file_name = get_input_file_name(module_path!());
     let contents = read_to_string(format!("src/{}", input_file_name))?;
-    let mut mappings: HashMap<(&str, &str), Vec<Entry>> = HashMap::new();
-
-    let seeds: Vec<i64> = contents
-        .lines()
-        .next()
-        .map(parse_seeds)
-        .expect("seeds not on first line");
-
-    for chunk in contents.split("\n\n").skip(1) {
-        let (src, dist, entries) = parse_mapping(chunk);
-        mappings.insert((src, dist), entries);
-    }
+    let (seeds, mappings) = parse_almanac(&contents)?;
 
     let part_1_answer = solve_part_1(&seeds, &mappings);
     println!(
@@ -171,7 +643,91 @@ pub fn solve() -> Result<(), Error> {
     Ok(())
 }
 
-#[derive(Debug)]
+pub fn check_input() -> Result<(), Error> {
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    parse_almanac(&contents)?;
+    println!("{}: ok", input_file_name);
+    Ok(())
+}
+
+// The first argument that is neither a flag nor a flag's value, as `81` in `--from soil 81`.
+fn positional(args: &[String]) -> Option<&String> {
+    args.iter()
+        .enumerate()
+        .find(|(i, arg)| !arg.starts_with("--") && (*i == 0 || !args[i - 1].starts_with("--")))
+        .map(|(_, arg)| arg)
+}
+
+// `almanac map --from soil --to humidity 81` maps one value, `81..95` maps a range.
+pub fn map_command(args: &[String]) -> Result<(), Error> {
+    let invalid_input = |e: String| Error::new(ErrorKind::InvalidInput, e);
+    let from = flag_value(args, "--from").ok_or(invalid_input("missing --from".to_string()))?;
+    let to = flag_value(args, "--to").ok_or(invalid_input("missing --to".to_string()))?;
+    let value = positional(args).ok_or(invalid_input("missing value to map".to_string()))?;
+    let parse = |n: &str| {
+        n.parse::<i64>()
+            .map_err(|_| invalid_input(format!("invalid value {:?}", n)))
+    };
+
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    let (_, mappings) = parse_almanac(&contents)?;
+    let almanac_error = |e: AlmanacError| Error::new(ErrorKind::InvalidInput, e);
+
+    match value.split_once("..") {
+        Some((start, end)) => {
+            let range = parse(start)?..parse(end)?;
+            if range.is_empty() {
+                return Err(invalid_input(format!("empty range {:?}", value)));
+            }
+            let ranges = map_range(from, to, range, &mappings).map_err(almanac_error)?;
+            for range in ranges {
+                println!("{} {}..{}", to, range.start, range.end);
+            }
+        }
+        None => {
+            let mapped = map_value(from, to, parse(value)?, &mappings).map_err(almanac_error)?;
+            println!("{} {}", to, mapped);
+        }
+    }
+    Ok(())
+}
+
+// `almanac trace 79` follows seed 79 through every map; `--from soil` starts elsewhere.
+pub fn trace_command(args: &[String]) -> Result<(), Error> {
+    let invalid_input = |e: String| Error::new(ErrorKind::InvalidInput, e);
+    let from = flag_value(args, "--from").unwrap_or("seed");
+    let value = positional(args).ok_or(invalid_input("missing value to trace".to_string()))?;
+    let value = value
+        .parse::<i64>()
+        .map_err(|_| invalid_input(format!("invalid value {:?}", value)))?;
+
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    let (_, mappings) = parse_almanac(&contents)?;
+    if !mappings.keys().any(|(src, _)| *src == from) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            AlmanacError::UnknownCategory(from.to_string()),
+        ));
+    }
+    println!("{}", format_trace(&trace_mappings(from, value, &mappings)));
+    Ok(())
+}
+
+// `almanac svg [file]` writes the layers and seed ranges to `almanac.svg` or `file`.
+pub fn svg_command(args: &[String]) -> Result<(), Error> {
+    let output = args.first().map(String::as_str).unwrap_or("almanac.svg");
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    let (seeds, mappings) = parse_almanac(&contents)?;
+    write(output, render_svg(&seeds, &mappings))?;
+    println!("wrote {}", output);
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
 struct Entry {
     source: i64,
     dist: i64,
@@ -179,155 +735,152 @@ struct Entry {
 }
 
 impl Entry {
+    fn source_range(&self) -> Range<i64> {
+        self.source..self.source + self.range
+    }
+    fn dist_range(&self) -> Range<i64> {
+        self.dist..self.dist + self.range
+    }
+    // A value too far away for the difference to fit is outside the entry either way.
     fn get_dist_value(&self, src_value: i64) -> Option<i64> {
-        if src_value >= self.source && src_value <= self.source + self.range {
-            let diff = src_value - self.source;
-            return Some(self.dist + diff);
-        }
-        None
+        let diff = src_value.checked_sub(self.source)?;
+        (0..self.range).contains(&diff).then(|| self.dist + diff)
     }
     fn get_src_value(&self, dist_value: i64) -> Option<i64> {
-        let diff = dist_value - self.dist;
-        let src = self.source + diff;
-        if src >= self.source && src <= self.source + self.range {
-            return Some(src);
-        }
-        None
+        let diff = dist_value.checked_sub(self.dist)?;
+        (0..self.range).contains(&diff).then(|| self.source + diff)
     }
 }
 
 #[test]
 fn test_reverse_traverse_mappings() {
-    let mappings: HashMap<(&str, &str), Vec<Entry>> = HashMap::from_iter(
-        vec![
-            (
-                ("seed", "soil"),
-                vec![
-                    Entry {
-                        source: 98,
-                        dist: 50,
-                        range: 2,
-                    },
-                    Entry {
-                        source: 50,
-                        dist: 52,
-                        range: 48,
-                    },
-                ],
-            ),
-            (
-                ("soil", "fertilizer"),
-                vec![
-                    Entry {
-                        source: 15,
-                        dist: 0,
-                        range: 37,
-                    },
-                    Entry {
-                        source: 52,
-                        dist: 37,
-                        range: 2,
-                    },
-                    Entry {
-                        source: 0,
-                        dist: 39,
-                        range: 15,
-                    },
-                ],
-            ),
-            (
-                ("fertilizer", "water"),
-                vec![
-                    Entry {
-                        source: 53,
-                        dist: 49,
-                        range: 8,
-                    },
-                    Entry {
-                        source: 11,
-                        dist: 0,
-                        range: 42,
-                    },
-                    Entry {
-                        source: 0,
-                        dist: 42,
-                        range: 7,
-                    },
-                    Entry {
-                        source: 7,
-                        dist: 57,
-                        range: 4,
-                    },
-                ],
-            ),
-            (
-                ("water", "light"),
-                vec![
-                    Entry {
-                        source: 18,
-                        dist: 88,
-                        range: 7,
-                    },
-                    Entry {
-                        source: 25,
-                        dist: 18,
-                        range: 70,
-                    },
-                ],
-            ),
-            (
-                ("light", "temperature"),
-                vec![
-                    Entry {
-                        source: 77,
-                        dist: 45,
-                        range: 23,
-                    },
-                    Entry {
-                        source: 45,
-                        dist: 81,
-                        range: 19,
-                    },
-                    Entry {
-                        source: 64,
-                        dist: 68,
-                        range: 13,
-                    },
-                ],
-            ),
-            (
-                ("temperature", "humidity"),
-                vec![
-                    Entry {
-                        source: 69,
-                        dist: 0,
-                        range: 1,
-                    },
-                    Entry {
-                        source: 0,
-                        dist: 1,
-                        range: 69,
-                    },
-                ],
-            ),
-            (
-                ("humidity", "location"),
-                vec![
-                    Entry {
-                        source: 56,
-                        dist: 60,
-                        range: 37,
-                    },
-                    Entry {
-                        source: 93,
-                        dist: 56,
-                        range: 4,
-                    },
-                ],
-            ),
-        ]
-        .into_iter(),
-    );
+    let mappings: HashMap<(&str, &str), Vec<Entry>> = HashMap::from_iter(vec![
+        (
+            ("seed", "soil"),
+            vec![
+                Entry {
+                    source: 98,
+                    dist: 50,
+                    range: 2,
+                },
+                Entry {
+                    source: 50,
+                    dist: 52,
+                    range: 48,
+                },
+            ],
+        ),
+        (
+            ("soil", "fertilizer"),
+            vec![
+                Entry {
+                    source: 15,
+                    dist: 0,
+                    range: 37,
+                },
+                Entry {
+                    source: 52,
+                    dist: 37,
+                    range: 2,
+                },
+                Entry {
+                    source: 0,
+                    dist: 39,
+                    range: 15,
+                },
+            ],
+        ),
+        (
+            ("fertilizer", "water"),
+            vec![
+                Entry {
+                    source: 53,
+                    dist: 49,
+                    range: 8,
+                },
+                Entry {
+                    source: 11,
+                    dist: 0,
+                    range: 42,
+                },
+                Entry {
+                    source: 0,
+                    dist: 42,
+                    range: 7,
+                },
+                Entry {
+                    source: 7,
+                    dist: 57,
+                    range: 4,
+                },
+            ],
+        ),
+        (
+            ("water", "light"),
+            vec![
+                Entry {
+                    source: 18,
+                    dist: 88,
+                    range: 7,
+                },
+                Entry {
+                    source: 25,
+                    dist: 18,
+                    range: 70,
+                },
+            ],
+        ),
+        (
+            ("light", "temperature"),
+            vec![
+                Entry {
+                    source: 77,
+                    dist: 45,
+                    range: 23,
+                },
+                Entry {
+                    source: 45,
+                    dist: 81,
+                    range: 19,
+                },
+                Entry {
+                    source: 64,
+                    dist: 68,
+                    range: 13,
+                },
+            ],
+        ),
+        (
+            ("temperature", "humidity"),
+            vec![
+                Entry {
+                    source: 69,
+                    dist: 0,
+                    range: 1,
+                },
+                Entry {
+                    source: 0,
+                    dist: 1,
+                    range: 69,
+                },
+            ],
+        ),
+        (
+            ("humidity", "location"),
+            vec![
+                Entry {
+                    source: 56,
+                    dist: 60,
+                    range: 37,
+                },
+                Entry {
+                    source: 93,
+                    dist: 56,
+                    range: 4,
+                },
+            ],
+        ),
+    ]);
     assert_eq!(traverse_mappings("seed", 79, &mappings), 82);
     assert_eq!(traverse_mappings("seed", 14, &mappings), 43);
     assert_eq!(traverse_mappings("seed", 55, &mappings), 86);
@@ -347,11 +900,300 @@ fn test_entry() {
     };
     assert_eq!(entry.get_dist_value(98), Some(50));
     assert_eq!(entry.get_dist_value(99), Some(51));
-    assert_eq!(entry.get_dist_value(100), Some(52));
-    assert_eq!(entry.get_dist_value(101), None);
+    assert_eq!(entry.get_dist_value(100), None);
+    assert_eq!(entry.get_dist_value(97), None);
 
     assert_eq!(entry.get_src_value(50), Some(98));
     assert_eq!(entry.get_src_value(51), Some(99));
-    assert_eq!(entry.get_src_value(52), Some(100));
-    assert_eq!(entry.get_src_value(53), None);
+    assert_eq!(entry.get_src_value(52), None);
+    assert_eq!(entry.get_src_value(49), None);
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+
+#[test]
+fn test_map_value() {
+    let (_, mappings) = parse_almanac(EXAMPLE).unwrap();
+    assert_eq!(map_value("seed", "location", 79, &mappings), Ok(82));
+    assert_eq!(map_value("seed", "seed", 79, &mappings), Ok(79));
+    // seed 79 is soil 81, fertilizer 81, water 81, light 74, temperature 78 and humidity 78
+    assert_eq!(map_value("soil", "humidity", 81, &mappings), Ok(78));
+    assert_eq!(map_value("humidity", "soil", 78, &mappings), Ok(81));
+    assert_eq!(map_value("location", "seed", 82, &mappings), Ok(79));
+
+    assert_eq!(
+        map_value("soil", "moisture", 81, &mappings),
+        Err(AlmanacError::UnknownCategory("moisture".to_string()))
+    );
+    // values too far from an entry for the difference to fit pass through unchanged
+    assert_eq!(map_value("soil", "seed", i64::MIN, &mappings), Ok(i64::MIN));
+    assert_eq!(
+        map_value("seed", "location", i64::MAX, &mappings),
+        Ok(i64::MAX)
+    );
+
+    let (_, mut mappings) = parse_almanac(EXAMPLE).unwrap();
+    mappings.remove(&("water", "light"));
+    assert_eq!(
+        map_value("soil", "humidity", 81, &mappings),
+        Err(AlmanacError::NotConnected {
+            from: "soil".to_string(),
+            to: "humidity".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_map_range() {
+    let (_, mappings) = parse_almanac(EXAMPLE).unwrap();
+    // 45..55 straddles the end of the `52 50 48` entry and the start of nothing
+    assert_eq!(
+        map_range("seed", "soil", 45..55, &mappings),
+        Ok(vec![45..50, 52..57])
+    );
+    let seeds = map_range("soil", "seed", 60..70, &mappings).unwrap();
+    assert_eq!(seeds.len(), 1);
+    assert_eq!(seeds[0], 58..68);
+
+    let locations = map_range("seed", "location", 79..93, &mappings).unwrap();
+    for seed in 79..93 {
+        let location = traverse_mappings("seed", seed, &mappings);
+        assert!(locations.iter().any(|range| range.contains(&location)));
+    }
+    assert_eq!(
+        locations
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum::<i64>(),
+        14
+    );
+}
+
+#[test]
+fn test_trace_mappings() {
+    let (_, mappings) = parse_almanac(EXAMPLE).unwrap();
+    let steps = trace_mappings("seed", 79, &mappings);
+    assert_eq!(
+        steps[..3],
+        [
+            TraceStep {
+                category: "seed",
+                value: 79,
+                source: TraceSource::Start,
+            },
+            TraceStep {
+                category: "soil",
+                value: 81,
+                source: TraceSource::Entry(&Entry {
+                    source: 50,
+                    dist: 52,
+                    range: 48,
+                }),
+            },
+            TraceStep {
+                category: "fertilizer",
+                value: 81,
+                source: TraceSource::Unchanged,
+            },
+        ]
+    );
+    assert_eq!(
+        steps.last().unwrap().value,
+        traverse_mappings("seed", 79, &mappings)
+    );
+    assert_eq!(
+        format_trace(&steps),
+        "seed 79 → soil 81 (entry 52 50 48) → fertilizer 81 (unchanged) → \
+         water 81 (unchanged) → light 74 (entry 18 25 70) → \
+         temperature 78 (entry 68 64 13) → humidity 78 (unchanged) → \
+         location 82 (entry 60 56 37)"
+    );
+}
+
+#[test]
+fn test_render_svg() {
+    let (seeds, mappings) = parse_almanac(EXAMPLE).unwrap();
+    let svg = render_svg(&seeds, &mappings);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.ends_with("</svg>\n"));
+    for category in ["seed", "soil", "humidity", "location"] {
+        assert!(svg.contains(&format!(">{}</text>", category)));
+    }
+    let entries = mappings.values().map(Vec::len).sum::<usize>();
+    assert_eq!(svg.matches("fill=\"#999\"").count(), entries);
+    // seed ranges 79..93 and 55..68 split as they flow down
+    assert!(svg.matches("fill=\"#1f77b4\"").count() > 2 * mappings.len());
+}
+
+#[test]
+fn test_split_ranges_through() {
+    let (_, mappings) = parse_almanac(EXAMPLE).unwrap();
+    let entries = &mappings[&("seed", "soil")];
+    assert_eq!(
+        split_ranges_through(vec![45..55, 99..101], entries, Direction::Forward),
+        vec![
+            (45..50, 45..50),
+            (50..55, 52..57),
+            (99..100, 51..52),
+            (100..101, 100..101)
+        ]
+    );
+    // the two ends of this entry are further apart than an i64 reaches
+    let entries = [Entry {
+        source: i64::MIN,
+        dist: i64::MAX - 10,
+        range: 10,
+    }];
+    let (low, high) = (i64::MIN + 5..i64::MIN + 20, i64::MAX - 3..i64::MAX);
+    assert_eq!(
+        split_ranges_through(vec![low], &entries, Direction::Forward),
+        vec![
+            (i64::MIN + 5..i64::MIN + 10, i64::MAX - 5..i64::MAX),
+            (i64::MIN + 10..i64::MIN + 20, i64::MIN + 10..i64::MIN + 20)
+        ]
+    );
+    assert_eq!(
+        split_ranges_through(vec![high], &entries, Direction::Backward),
+        vec![(i64::MAX - 3..i64::MAX, i64::MIN + 7..i64::MIN + 10)]
+    );
+}
+
+#[test]
+fn test_positional() {
+    let args = |s: &str| s.split(' ').map(String::from).collect::<Vec<String>>();
+    assert_eq!(
+        positional(&args("81 --from soil --to humidity")),
+        Some(&"81".to_string())
+    );
+    assert_eq!(
+        positional(&args("--from soil 81 --to humidity")),
+        Some(&"81".to_string())
+    );
+    assert_eq!(
+        positional(&args("--from soil --to seed -9223372036854775808")),
+        Some(&"-9223372036854775808".to_string())
+    );
+    assert_eq!(positional(&args("--from soil --to humidity")), None);
+}
+
+#[test]
+fn test_validate_almanac() {
+    assert_eq!(read_almanac(EXAMPLE).2, vec![]);
+
+    let almanac = "seeds: 79 14
+
+seed-to-soil map:
+50 98 2
+52 50 49
+10 0 0
+
+seed-to-fertilizer map:
+0 9223372036854775800 10
+1 2
+
+soil-to-water map:
+0 0 10
+5 20 5
+9 3 30
+";
+    assert_eq!(
+        read_almanac(almanac).2,
+        vec![
+            AlmanacIssue::NonPositiveRange { line: 6, range: 0 },
+            AlmanacIssue::Overlap {
+                line: 5,
+                other_line: 4,
+            },
+            AlmanacIssue::DuplicateHeader {
+                line: 8,
+                first_line: 3,
+            },
+            AlmanacIssue::Overflow { line: 9 },
+            AlmanacIssue::Malformed {
+                line: 10,
+                reason: "expected `<destination> <source> <length>`".to_string(),
+            },
+            AlmanacIssue::Overlap {
+                line: 15,
+                other_line: 13,
+            },
+            AlmanacIssue::Overlap {
+                line: 15,
+                other_line: 14,
+            },
+        ]
+    );
+    assert_eq!(
+        read_almanac("seeds: 79 14 55\n").2,
+        vec![AlmanacIssue::Malformed {
+            line: 1,
+            reason: "expected seed ranges as `<start> <length>` pairs".to_string(),
+        }]
+    );
+    assert_eq!(
+        read_almanac("seeds: 79 14 9223372036854775800 10\n").2,
+        vec![AlmanacIssue::Overflow { line: 1 }]
+    );
+    assert_eq!(
+        AlmanacIssue::Overlap {
+            line: 5,
+            other_line: 4,
+        }
+        .to_string(),
+        "line 5: source range overlaps the entry on line 4"
+    );
+}
+
+#[test]
+fn test_parse_almanac_whitespace() {
+    let expected = parse_almanac(EXAMPLE).unwrap();
+    let variants = [
+        EXAMPLE.replace("50 98 2", "50  98 2"),
+        EXAMPLE.replace("50 98 2", "50 98 2 "),
+        EXAMPLE.replace("seeds: ", "seeds:"),
+        EXAMPLE.replace('\n', "\r\n"),
+    ];
+    for almanac in variants.iter() {
+        assert_eq!(parse_almanac(almanac).unwrap(), expected, "{:?}", almanac);
+    }
+
+    let error = parse_almanac("seeds: 79 x\n\nseed-to-soil map:\n50 98\n").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "line 1: expected `seeds: <numbers>`\n\
+         line 4: expected `<destination> <source> <length>`"
+    );
 }
@@ -1,84 +1,445 @@
-use crate::utils::get_input_file_name;
+use crate::utils::{flag_value, get_input_file_name, has_flag};
+use std::fmt;
 use std::fs::read_to_string;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 
 // Time:      7  15   30
 // Distance:  9  40  200
 
-fn parse_line(line: &str) -> Vec<i64> {
-    line.split(':')
-        .nth(1)
-        .map(|s| s.split(' ').filter_map(|token| token.parse::<i64>().ok()))
-        .expect("malformed line")
-        .collect::<Vec<i64>>()
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Race {
+    time: i64,
+    distance: i64,
 }
 
-fn num_ways_to_win(time: i64, distance: i64) -> i64 {
-    // t (time)
-    // r (record)
-    // h (hold time) 0..t
-    // h * (t - h) > r
-    // ht - h^2 > r
-    // ht - h^2 - r > 0
-    // - h^2 + th - r > 0
-    // this is a quadratic formula that follows this equation  ax^2 + bx + c = 0
-    // we need to solve for x
-    let a = -1f64;
-    let b = time as f64;
-    let c = -distance as f64;
-    let mut first_solution = ((-b + (b * b - 4. * a * c).sqrt()) / 2. * a).ceil();
-    let mut second_solution = ((-b - (b * b - 4. * a * c).sqrt()) / 2. * a).floor();
-    // now since we need to solve for > 0 not = 0, which means our solutions shouldn't be equal to
-    // distance record;
-    if first_solution * (b - first_solution) == distance as f64 {
-        first_solution += 1.;
+// Part 1 reads every column as its own race, part 2 ignores the spaces between them and reads
+// one long race.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Interpretation {
+    Separate,
+    Kerned,
+}
+
+#[derive(Debug, PartialEq)]
+struct RaceTable {
+    times: Vec<i64>,
+    distances: Vec<i64>,
+}
+
+// Lines are 1-based.
+#[derive(Debug, PartialEq)]
+enum RaceTableError {
+    MissingLine { header: &'static str },
+    BadHeader { line: usize, header: &'static str },
+    InvalidNumber { line: usize, token: String },
+    NoRaces,
+    ColumnMismatch { times: usize, distances: usize },
+    KerningOverflow { header: &'static str },
+}
+
+impl fmt::Display for RaceTableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RaceTableError::MissingLine { header } => write!(f, "missing `{}` line", header),
+            RaceTableError::BadHeader { line, header } => {
+                write!(f, "line {}: expected it to start with `{}`", line, header)
+            }
+            RaceTableError::InvalidNumber { line, token } => {
+                write!(f, "line {}: invalid number {:?}", line, token)
+            }
+            RaceTableError::NoRaces => write!(f, "the table has no races"),
+            RaceTableError::ColumnMismatch { times, distances } => {
+                write!(f, "{} times but {} distances", times, distances)
+            }
+            RaceTableError::KerningOverflow { header } => {
+                write!(f, "kerned `{}` value does not fit in an i64", header)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RaceTableError {}
+
+const TIME_HEADER: &str = "Time:";
+const DISTANCE_HEADER: &str = "Distance:";
+
+fn parse_row(
+    line: Option<(usize, &str)>,
+    header: &'static str,
+) -> Result<Vec<i64>, RaceTableError> {
+    let (line, text) = line.ok_or(RaceTableError::MissingLine { header })?;
+    text.strip_prefix(header)
+        .ok_or(RaceTableError::BadHeader { line, header })?
+        .split_whitespace()
+        .map(|token| {
+            token
+                .parse::<i64>()
+                .ok()
+                .filter(|n| *n >= 0)
+                .ok_or(RaceTableError::InvalidNumber {
+                    line,
+                    token: token.to_string(),
+                })
+        })
+        .collect()
+}
+
+impl FromStr for RaceTable {
+    type Err = RaceTableError;
+
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        let mut lines = contents.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let times = parse_row(lines.next(), TIME_HEADER)?;
+        let distances = parse_row(lines.next(), DISTANCE_HEADER)?;
+        if times.len() != distances.len() {
+            return Err(RaceTableError::ColumnMismatch {
+                times: times.len(),
+                distances: distances.len(),
+            });
+        }
+        if times.is_empty() {
+            return Err(RaceTableError::NoRaces);
+        }
+        Ok(RaceTable { times, distances })
+    }
+}
+
+// Writes the numbers one after the other, as if the spaces between them were not there.
+fn kern(nums: &[i64], header: &'static str) -> Result<i64, RaceTableError> {
+    nums.iter()
+        .try_fold(0i64, |acc, n| {
+            let digits = n.checked_ilog10().unwrap_or(0) + 1;
+            acc.checked_mul(10i64.checked_pow(digits)?)?.checked_add(*n)
+        })
+        .ok_or(RaceTableError::KerningOverflow { header })
+}
+
+impl RaceTable {
+    fn races(&self, interpretation: Interpretation) -> Result<Vec<Race>, RaceTableError> {
+        match interpretation {
+            Interpretation::Separate => Ok(self
+                .times
+                .iter()
+                .zip(self.distances.iter())
+                .map(|(time, distance)| Race {
+                    time: *time,
+                    distance: *distance,
+                })
+                .collect()),
+            Interpretation::Kerned => Ok(vec![Race {
+                time: kern(&self.times, TIME_HEADER)?,
+                distance: kern(&self.distances, DISTANCE_HEADER)?,
+            }]),
+        }
+    }
+}
+
+fn parse_races(contents: &str, interpretation: Interpretation) -> Result<Vec<Race>, Error> {
+    contents
+        .parse::<RaceTable>()
+        .and_then(|table| table.races(interpretation))
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+// Every ms the button is held adds `acceleration` mm/ms to the boat's speed, up to
+// `max_charge` ms after which holding on only wastes time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RaceModel {
+    acceleration: i64,
+    max_charge: Option<i64>,
+}
+
+impl Default for RaceModel {
+    fn default() -> Self {
+        RaceModel {
+            acceleration: 1,
+            max_charge: None,
+        }
+    }
+}
+
+impl RaceModel {
+    // Saturates rather than overflowing for huge accelerations: a saturated distance still beats
+    // any i64 record, so the winning holds come out the same.
+    fn distance(&self, hold: i64, time: i64) -> i128 {
+        let charge = self.max_charge.map_or(hold, |max| hold.min(max));
+        (self.acceleration as i128)
+            .saturating_mul(charge as i128)
+            .saturating_mul((time - hold) as i128)
+    }
+
+    // The distance rises until the charge stops paying off and falls after that, so the
+    // winning hold times form a single interval around that peak.
+    fn winning_holds(&self, race: &Race) -> Option<RangeInclusive<i64>> {
+        if race.time < 0 {
+            return None;
+        }
+        let wins = |hold: i64| self.distance(hold, race.time) > race.distance as i128;
+        let peak = self
+            .max_charge
+            .map_or(race.time / 2, |max| max.clamp(0, race.time / 2));
+        if !wins(peak) {
+            return None;
+        }
+        // first hold time in lo..=hi for which `pred` holds, given it holds for hi and is
+        // monotone over the range
+        let first = |mut lo: i64, mut hi: i64, pred: &dyn Fn(i64) -> bool| {
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                if pred(mid) {
+                    hi = mid;
+                } else {
+                    lo = mid + 1;
+                }
+            }
+            lo
+        };
+        let start = first(0, peak, &wins);
+        let end = first(peak, race.time, &|hold| {
+            !wins(hold + 1) || hold == race.time
+        });
+        Some(start..=end)
     }
-    if second_solution * (b - second_solution) == distance as f64 {
-        second_solution -= 1.;
+
+    fn num_ways_to_win(&self, race: &Race) -> i64 {
+        self.winning_holds(race)
+            .map_or(0, |holds| holds.end() - holds.start() + 1)
     }
-    (second_solution - first_solution) as i64 + 1
+}
+
+fn num_ways_to_win(time: i64, distance: i64) -> i64 {
+    RaceModel::default().num_ways_to_win(&Race { time, distance })
 }
 
 pub fn solve() -> Result<(), Error> {
     let input_file_name = get_input_file_name(module_path!());
     let contents = read_to_string(format!("src/{}", input_file_name))?;
 
-    let times = contents
-        .lines().next()
-        .map(parse_line)
-        .expect("malformed input");
-    let distances = contents
-        .lines()
-        .nth(1)
-        .map(parse_line)
-        .expect("malformed input");
-    let res = times
+    let res = parse_races(&contents, Interpretation::Separate)?
         .iter()
-        .zip(distances.clone())
-        .fold(1, |acc, (t, d)| acc * num_ways_to_win(*t, d));
+        .fold(1, |acc, race| {
+            acc * num_ways_to_win(race.time, race.distance)
+        });
     println!("module: {}, part 1, result: {}", module_path!(), res);
 
-    let time = times
-        .iter()
-        .map(|i| format!("{i}"))
-        .collect::<String>()
-        .parse::<i64>()
-        .unwrap();
-    let distance = distances
+    let res2 = parse_races(&contents, Interpretation::Kerned)?
         .iter()
-        .map(|i| format!("{i}"))
-        .collect::<String>()
-        .parse::<i64>()
-        .unwrap();
-    let res2 = num_ways_to_win(time, distance);
+        .fold(1, |acc, race| {
+            acc * num_ways_to_win(race.time, race.distance)
+        });
     println!("module: {}, part 2, result: {}", module_path!(), res2);
 
     Ok(())
 }
 
+// `race 6 --acceleration 2 --max-charge 10 --kerned` prints the winning hold times of every race.
+pub fn race_command(args: &[String]) -> Result<(), Error> {
+    let parse_flag = |flag: &str| {
+        flag_value(args, flag)
+            .map(|n| {
+                n.parse::<i64>().map_err(|_| {
+                    Error::new(ErrorKind::InvalidInput, format!("invalid {} {:?}", flag, n))
+                })
+            })
+            .transpose()
+    };
+    let model = RaceModel {
+        acceleration: parse_flag("--acceleration")?.unwrap_or(1),
+        max_charge: parse_flag("--max-charge")?,
+    };
+    let interpretation = if has_flag(args, "--kerned") {
+        Interpretation::Kerned
+    } else {
+        Interpretation::Separate
+    };
+
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    for race in parse_races(&contents, interpretation)? {
+        match model.winning_holds(&race) {
+            Some(holds) => println!(
+                "time {}, record {}: hold {}..={} ms, {} ways to win",
+                race.time,
+                race.distance,
+                holds.start(),
+                holds.end(),
+                model.num_ways_to_win(&race)
+            ),
+            None => println!(
+                "time {}, record {}: no way to win",
+                race.time, race.distance
+            ),
+        }
+    }
+    Ok(())
+}
+
 #[test]
 fn test_num_ways_to_win() {
     assert_eq!(num_ways_to_win(7, 9), 4);
     assert_eq!(num_ways_to_win(15, 40), 8);
     assert_eq!(num_ways_to_win(30, 200), 9);
 }
+
+#[test]
+fn test_race_table() {
+    let contents = "Time:      7  15   30\nDistance:  9  40  200";
+    let table = contents.parse::<RaceTable>().unwrap();
+    assert_eq!(
+        table.races(Interpretation::Separate),
+        Ok(vec![
+            Race {
+                time: 7,
+                distance: 9
+            },
+            Race {
+                time: 15,
+                distance: 40
+            },
+            Race {
+                time: 30,
+                distance: 200
+            },
+        ])
+    );
+    let kerned = table.races(Interpretation::Kerned).unwrap();
+    assert_eq!(
+        kerned,
+        vec![Race {
+            time: 71530,
+            distance: 940200
+        }]
+    );
+    assert_eq!(RaceModel::default().num_ways_to_win(&kerned[0]), 71503);
+}
+
+#[test]
+fn test_winning_holds() {
+    let model = RaceModel::default();
+    let race = Race {
+        time: 30,
+        distance: 200,
+    };
+    assert_eq!(model.winning_holds(&race), Some(11..=19));
+    assert_eq!(
+        model.winning_holds(&Race {
+            time: 7,
+            distance: 12
+        }),
+        None
+    );
+
+    // at 2 mm/ms per ms held, holding h ms goes 2h(7 - h): 0, 12, 20, 24, 24, 20, 12, 0
+    let model = RaceModel {
+        acceleration: 2,
+        max_charge: None,
+    };
+    assert_eq!(
+        model.winning_holds(&Race {
+            time: 7,
+            distance: 12
+        }),
+        Some(2..=5)
+    );
+
+    // charging stops paying off after 2 ms: 0, 6, 10, 8, 6, 4, 2, 0
+    let model = RaceModel {
+        acceleration: 1,
+        max_charge: Some(2),
+    };
+    assert_eq!(
+        model.winning_holds(&Race {
+            time: 7,
+            distance: 5
+        }),
+        Some(1..=4)
+    );
+    // the distance saturates instead of overflowing, and a saturated distance still wins
+    let model = RaceModel {
+        acceleration: i64::MAX,
+        max_charge: None,
+    };
+    assert_eq!(model.distance(i64::MAX / 2, i64::MAX), i128::MAX);
+    assert_eq!(
+        model.winning_holds(&Race {
+            time: i64::MAX,
+            distance: i64::MAX
+        }),
+        Some(1..=i64::MAX - 1)
+    );
+
+    // brute force over every hold time agrees with the interval
+    for time in 0..40 {
+        for distance in 0..120 {
+            for model in [
+                RaceModel::default(),
+                RaceModel {
+                    acceleration: 3,
+                    max_charge: Some(time / 3),
+                },
+            ] {
+                let race = Race { time, distance };
+                let wins = (0..=time)
+                    .filter(|hold| model.distance(*hold, time) > distance as i128)
+                    .count() as i64;
+                assert_eq!(model.num_ways_to_win(&race), wins, "{:?} {:?}", model, race);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_race_table_errors() {
+    let cases = [
+        ("", RaceTableError::MissingLine { header: "Time:" }),
+        (
+            "Time: 7 15",
+            RaceTableError::MissingLine {
+                header: "Distance:",
+            },
+        ),
+        (
+            "Times: 7 15\nDistance: 9 40",
+            RaceTableError::BadHeader {
+                line: 1,
+                header: "Time:",
+            },
+        ),
+        (
+            "Time: 7 15\nDistance: 9 4O",
+            RaceTableError::InvalidNumber {
+                line: 2,
+                token: "4O".to_string(),
+            },
+        ),
+        (
+            "Time: 7 -15\nDistance: 9 40",
+            RaceTableError::InvalidNumber {
+                line: 1,
+                token: "-15".to_string(),
+            },
+        ),
+        (
+            "Time: 7 15 30\nDistance: 9 40",
+            RaceTableError::ColumnMismatch {
+                times: 3,
+                distances: 2,
+            },
+        ),
+        ("Time:\nDistance:", RaceTableError::NoRaces),
+    ];
+    for (contents, error) in cases {
+        assert_eq!(contents.parse::<RaceTable>(), Err(error));
+    }
+
+    let table = "Time: 1234567890 1234567890\nDistance: 1 2"
+        .parse::<RaceTable>()
+        .unwrap();
+    assert_eq!(
+        table.races(Interpretation::Kerned),
+        Err(RaceTableError::KerningOverflow { header: "Time:" })
+    );
+    assert_eq!(kern(&[7, 0, 15], "Time:"), Ok(7015));
+}
@@ -1,9 +1,15 @@
 use crate::utils::get_input_file_name;
+#[cfg(test)]
+use crate::utils::Rng;
+use std::cmp::Ordering;
+#[cfg(test)]
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::read_to_string;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
 
-#[derive(Clone, Debug, PartialEq, PartialOrd, Ord, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Ord, Eq)]
 enum HandType {
     // all cards' labels are distinct
     HighCard,
@@ -19,31 +25,26 @@ enum HandType {
 }
 
 impl HandType {
-    fn from_cards(cards: &Vec<Card>, has_wild_card: bool) -> Self {
-        let mut group = HashMap::new();
-        for c in cards {
-            group.entry(c).and_modify(|v| *v += 1).or_insert(1);
-        }
+    fn from_cards(cards: &[Card], has_wild_card: bool) -> Self {
+        let mut group = card_counts(cards);
         if has_wild_card {
-            if let Some(num_j) = group.clone().get(&Card::Joker) {
-                if *num_j < 5 {
-                    group.retain(|k, _| **k != Card::Joker);
-                    let max_card = group.iter().max_by(|a, b| a.1.cmp(b.1)).unwrap().0;
-                    group.entry(*max_card).and_modify(|v| *v += num_j);
-                }
+            if let Some(max_card) = joker_substitute(cards) {
+                group[max_card as usize] += group[Card::Joker as usize];
+                group[Card::Joker as usize] = 0;
             }
         }
-        match group.len() {
+        let counts = group.iter().filter(|v| **v > 0);
+        match counts.clone().count() {
             1 => HandType::FiveOfKind,
             2 => {
-                if group.values().any(|v| *v == 1) {
+                if counts.clone().any(|v| *v == 1) {
                     HandType::FourOfKind
                 } else {
                     HandType::FullHouse
                 }
             }
             3 => {
-                if group.values().any(|v| *v == 3) {
+                if counts.clone().any(|v| *v == 3) {
                     HandType::ThreeOfKind
                 } else {
                     HandType::TwoPair
@@ -56,7 +57,68 @@ impl HandType {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+// The classifier `HandType::from_cards` replaced, grouping cards in a `HashMap`, kept as the
+// reference it is tested against.
+#[cfg(test)]
+fn reference_hand_type(cards: &[Card], has_wild_card: bool) -> HandType {
+    let mut group = HashMap::new();
+    for c in cards {
+        group.entry(c).and_modify(|v| *v += 1).or_insert(1);
+    }
+    if has_wild_card {
+        if let Some(num_j) = group.clone().get(&Card::Joker) {
+            if *num_j < 5 {
+                group.retain(|k, _| **k != Card::Joker);
+                let max_card = group.iter().max_by(|a, b| a.1.cmp(b.1)).unwrap().0;
+                group.entry(*max_card).and_modify(|v| *v += num_j);
+            }
+        }
+    }
+    match group.len() {
+        1 => HandType::FiveOfKind,
+        2 => {
+            if group.values().any(|v| *v == 1) {
+                HandType::FourOfKind
+            } else {
+                HandType::FullHouse
+            }
+        }
+        3 => {
+            if group.values().any(|v| *v == 3) {
+                HandType::ThreeOfKind
+            } else {
+                HandType::TwoPair
+            }
+        }
+        4 => HandType::OnePair,
+        5 => HandType::HighCard,
+        _ => panic!("malformed hand"),
+    }
+}
+
+// How many of each card the hand holds, indexed by card.
+fn card_counts(cards: &[Card]) -> [u8; 14] {
+    let mut group = [0u8; 14];
+    for c in cards {
+        group[*c as usize] += 1;
+    }
+    group
+}
+
+// The card the jokers of a hand count as: the one it holds most of, the highest of those on a
+// tie. A hand of only jokers keeps them as jokers.
+fn joker_substitute(cards: &[Card]) -> Option<Card> {
+    let group = card_counts(cards);
+    let num_j = group[Card::Joker as usize];
+    if num_j == 0 || num_j as usize == cards.len() {
+        return None;
+    }
+    (1..group.len())
+        .max_by_key(|c| group[*c])
+        .map(|c| Card::ALL[c])
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 enum Card {
     Joker,
     Two,
@@ -74,34 +136,235 @@ enum Card {
     A,
 }
 impl Card {
-    fn from_char(c: char, has_wild_card: bool) -> Card {
+    // in the same order as the variants, so `Card::ALL[card as usize] == card`
+    const ALL: [Card; 14] = [
+        Card::Joker,
+        Card::Two,
+        Card::Three,
+        Card::Four,
+        Card::Five,
+        Card::Six,
+        Card::Seven,
+        Card::Eight,
+        Card::Nine,
+        Card::Ten,
+        Card::J,
+        Card::Q,
+        Card::K,
+        Card::A,
+    ];
+
+    fn to_char(self) -> char {
+        match self {
+            Card::Joker | Card::J => 'J',
+            Card::Two => '2',
+            Card::Three => '3',
+            Card::Four => '4',
+            Card::Five => '5',
+            Card::Six => '6',
+            Card::Seven => '7',
+            Card::Eight => '8',
+            Card::Nine => '9',
+            Card::Ten => 'T',
+            Card::Q => 'Q',
+            Card::K => 'K',
+            Card::A => 'A',
+        }
+    }
+
+    // Under joker rules every J is a joker.
+    fn as_joker(self) -> Card {
+        match self {
+            Card::J => Card::Joker,
+            card => card,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseHandError {
+    UnknownCard(char),
+    WrongLength(usize),
+    InvalidBid(String),
+}
+
+impl fmt::Display for ParseHandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseHandError::UnknownCard(c) => write!(f, "unknown card {:?}", c),
+            ParseHandError::WrongLength(n) => write!(f, "a hand has 5 cards, not {}", n),
+            ParseHandError::InvalidBid(bid) => write!(f, "invalid bid {:?}", bid),
+        }
+    }
+}
+
+impl std::error::Error for ParseHandError {}
+
+// A `J` is the J card; `Card::as_joker` turns it into a joker.
+impl TryFrom<char> for Card {
+    type Error = ParseHandError;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            'J' => {
-                if has_wild_card {
-                    Card::Joker
-                } else {
-                    Card::J
+            '2' => Ok(Card::Two),
+            '3' => Ok(Card::Three),
+            '4' => Ok(Card::Four),
+            '5' => Ok(Card::Five),
+            '6' => Ok(Card::Six),
+            '7' => Ok(Card::Seven),
+            '8' => Ok(Card::Eight),
+            '9' => Ok(Card::Nine),
+            'T' => Ok(Card::Ten),
+            'J' => Ok(Card::J),
+            'Q' => Ok(Card::Q),
+            'K' => Ok(Card::K),
+            'A' => Ok(Card::A),
+            _ => Err(ParseHandError::UnknownCard(c)),
+        }
+    }
+}
+
+impl FromStr for Card {
+    type Err = ParseHandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Card::try_from(c),
+            _ => Err(ParseHandError::WrongLength(s.chars().count())),
+        }
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+// Orders hands like `Hand` does, packed into one integer: the hand type in bits 20 and up, then
+// four bits per card with the first card highest.
+fn sort_key(cards: &[Card; 5], has_wild_card: bool) -> u32 {
+    let hand_type = HandType::from_cards(cards, has_wild_card) as u32;
+    cards
+        .iter()
+        .fold(hand_type, |key, card| (key << 4) | *card as u32)
+}
+
+// Both sort keys of every hand with its bid, from a single pass over the input.
+fn parse_sort_keys(contents: &str) -> Result<Vec<(u32, u32, i64)>, Error> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(line_num, line)| {
+            let invalid_data = |e: ParseHandError| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: {}", line_num + 1, e),
+                )
+            };
+            let (cards_str, bid) = line.split_once(' ').unwrap_or((line, ""));
+            let bid = bid
+                .parse::<i64>()
+                .map_err(|_| invalid_data(ParseHandError::InvalidBid(bid.to_string())))?;
+            let mut cards = [Card::Two; 5];
+            let mut count = 0;
+            for c in cards_str.chars() {
+                if count < 5 {
+                    cards[count] = Card::try_from(c).map_err(invalid_data)?;
                 }
+                count += 1;
+            }
+            if count != 5 {
+                return Err(invalid_data(ParseHandError::WrongLength(count)));
             }
-            '2' => Card::Two,
-            '3' => Card::Three,
-            '4' => Card::Four,
-            '5' => Card::Five,
-            '6' => Card::Six,
-            '7' => Card::Seven,
-            '8' => Card::Eight,
-            '9' => Card::Nine,
-            'T' => Card::Ten,
-            'Q' => Card::Q,
-            'K' => Card::K,
-            'A' => Card::A,
-            _ => panic!("unknown card type"),
+            let wild_cards = cards.map(Card::as_joker);
+            Ok((sort_key(&cards, false), sort_key(&wild_cards, true), bid))
+        })
+        .collect()
+}
+
+fn total_winnings(mut ranked: Vec<(u32, i64)>) -> i64 {
+    ranked.sort_unstable();
+    ranked
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (i, (_, bid))| acc + (bid * (i as i64 + 1)))
+}
+
+#[derive(Debug, PartialEq)]
+struct HandExplanation {
+    hand_type: HandType,
+    // the card the jokers stood in for, under joker rules
+    joker_as: Option<Card>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Comparison {
+    hands: [HandExplanation; 2],
+    ordering: Ordering,
+    // position of the first card that differed, when both hands have the same type
+    tie_break: Option<usize>,
+}
+
+// Jokers only show up in hands read under joker rules, so the hands say which rules apply.
+fn compare_hands(a: &Hand, b: &Hand) -> Comparison {
+    let explain = |hand: &Hand| HandExplanation {
+        hand_type: hand.hand_type,
+        joker_as: joker_substitute(&hand.cards),
+    };
+    let hands = [explain(a), explain(b)];
+    let (ordering, tie_break) = match hands[0].hand_type.cmp(&hands[1].hand_type) {
+        Ordering::Equal => match (0..5).find(|i| a.cards[*i] != b.cards[*i]) {
+            Some(i) => (a.cards[i].cmp(&b.cards[i]), Some(i)),
+            None => (Ordering::Equal, None),
+        },
+        ordering => (ordering, None),
+    };
+    Comparison {
+        hands,
+        ordering,
+        tie_break,
+    }
+}
+
+fn format_comparison(a: &Hand, b: &Hand, comparison: &Comparison) -> String {
+    let label = |hand: &Hand| hand.cards.iter().map(Card::to_string).collect::<String>();
+    let mut res = String::new();
+    for (hand, explanation) in [a, b].iter().zip(comparison.hands.iter()) {
+        res.push_str(&format!("  {}: {:?}", label(hand), explanation.hand_type));
+        if let Some(card) = explanation.joker_as {
+            res.push_str(&format!(" (J as {})", card));
         }
+        res.push('\n');
+    }
+    let (winner, loser) = match comparison.ordering {
+        Ordering::Less => (1, 0),
+        _ => (0, 1),
+    };
+    let hands = [a, b];
+    match (comparison.ordering, comparison.tie_break) {
+        (Ordering::Equal, _) => res.push_str("  the hands are equal\n"),
+        (_, Some(i)) => res.push_str(&format!(
+            "  {} wins: same type, card {} {} beats {}\n",
+            label(hands[winner]),
+            i + 1,
+            hands[winner].cards[i],
+            hands[loser].cards[i]
+        )),
+        (_, None) => res.push_str(&format!(
+            "  {} wins: {:?} beats {:?}\n",
+            label(hands[winner]),
+            comparison.hands[winner].hand_type,
+            comparison.hands[loser].hand_type
+        )),
     }
+    res
 }
 
 // A hand consists of five cards labeled one of A, K, Q, J, T, 9, 8, 7, 6, 5, 4, 3, or 2.
 // The relative strength of each card follows this order, where A is the highest and 2 is the lowest.
+// `sort_key` packs the same ordering into an integer.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 struct Hand {
     hand_type: HandType,
@@ -110,57 +373,103 @@ struct Hand {
 }
 
 impl Hand {
+    #[cfg(test)]
     fn new(cards_str: String, bid: i64, has_wild_card: bool) -> Self {
-        let cards: Vec<Card> = cards_str
-            .chars()
-            .map(|c| Card::from_char(c, has_wild_card))
-            .collect::<Vec<Card>>();
+        let hand = format!("{} {}", cards_str, bid).parse::<Hand>().unwrap();
+        if has_wild_card {
+            hand.with_jokers()
+        } else {
+            hand
+        }
+    }
 
+    fn from_cards(cards: Vec<Card>, bid: i64) -> Self {
         Hand {
-            cards: cards.clone(),
+            // a hand without jokers has the same type either way
+            hand_type: HandType::from_cards(&cards, true),
+            cards,
             bid,
-            hand_type: HandType::from_cards(&cards, has_wild_card),
         }
     }
+
+    // The same hand under joker rules.
+    fn with_jokers(self) -> Self {
+        Hand::from_cards(
+            self.cards.into_iter().map(Card::as_joker).collect(),
+            self.bid,
+        )
+    }
+}
+
+// `32T3K 765`, or just `32T3K` for a hand with a bid of 0. Every J is read as the J card.
+impl FromStr for Hand {
+    type Err = ParseHandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (cards_str, bid) = match s.split_once(' ') {
+            Some((cards_str, bid)) => (
+                cards_str,
+                bid.parse::<i64>()
+                    .map_err(|_| ParseHandError::InvalidBid(bid.to_string()))?,
+            ),
+            None => (s, 0),
+        };
+        let cards = cards_str
+            .chars()
+            .map(Card::try_from)
+            .collect::<Result<Vec<Card>, ParseHandError>>()?;
+        if cards.len() != 5 {
+            return Err(ParseHandError::WrongLength(cards.len()));
+        }
+        Ok(Hand::from_cards(cards, bid))
+    }
+}
+
+impl fmt::Display for Hand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for card in self.cards.iter() {
+            write!(f, "{}", card)?;
+        }
+        write!(f, " {}", self.bid)
+    }
 }
 
 pub fn solve() -> Result<(), Error> {
     let input_file_name = get_input_file_name(module_path!());
     let contents = read_to_string(format!("src/{}", input_file_name))?;
 
-    let mut hands: Vec<Hand> = contents
-        .lines()
-        .map(|line| {
-            let (cards, bid) = line.split_once(' ').unwrap();
-            let bid = bid.parse::<i64>().unwrap();
-            Hand::new(cards.to_string(), bid, false)
-        })
-        .collect();
-    hands.sort();
-    let solution = hands
-        .iter()
-        .enumerate()
-        .fold(0, |acc, (i, hand)| acc + (hand.bid * (i as i64 + 1)));
+    let keys = parse_sort_keys(&contents)?;
+
+    let solution = total_winnings(keys.iter().map(|(key, _, bid)| (*key, *bid)).collect());
     println!("module: {}, part 1, result: {}", module_path!(), solution);
 
-    let mut hands_with_wc: Vec<Hand> = contents
-        .lines()
-        .map(|line| {
-            let (cards, bid) = line.split_once(' ').unwrap();
-            let bid = bid.parse::<i64>().unwrap();
-            Hand::new(cards.to_string(), bid, true)
-        })
-        .collect();
-    hands_with_wc.sort();
-    let solution = hands_with_wc
-        .iter()
-        .enumerate()
-        .fold(0, |acc, (i, hand)| acc + (hand.bid * (i as i64 + 1)));
+    let solution = total_winnings(keys.iter().map(|(_, key, bid)| (*key, *bid)).collect());
     println!("module: {}, part 2, result: {}", module_path!(), solution);
 
     Ok(())
 }
 
+// `explain 7 T55J5 KTJJT` compares two hands under both rule sets.
+pub fn explain(args: &[String]) -> Result<(), Error> {
+    let [a, b] = args else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "expected two hands to compare",
+        ));
+    };
+    let parse = |hand: &String| {
+        hand.parse::<Hand>()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+    };
+    let (a, b) = (parse(a)?, parse(b)?);
+    println!("without jokers:");
+    print!("{}", format_comparison(&a, &b, &compare_hands(&a, &b)));
+    let (a, b) = (a.with_jokers(), b.with_jokers());
+    println!("with jokers:");
+    print!("{}", format_comparison(&a, &b, &compare_hands(&a, &b)));
+    Ok(())
+}
+
 #[test]
 fn test_new_hand() {
     assert_eq!(
@@ -226,3 +535,162 @@ fn test_new_hand() {
         }
     );
 }
+
+#[test]
+fn test_sort_key_against_hand_ordering() {
+    let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+    let labels = "23456789TJQKA".chars().collect::<Vec<char>>();
+    // draw from a few labels at a time so pairs, full houses and jokers are common
+    let hands = (0..500)
+        .map(|_| {
+            let offset = rng.below(labels.len());
+            (0..5)
+                .map(|_| labels[(offset + rng.below(4)) % labels.len()])
+                .collect::<String>()
+        })
+        .chain(["JJJJJ", "AAAAA", "J2345", "T55J5", "KTJJT", "QQQJA"].map(String::from))
+        .collect::<Vec<String>>();
+    for has_wild_card in [false, true] {
+        let ranked = hands
+            .iter()
+            .map(|cards| {
+                let hand = Hand::new(cards.clone(), 0, has_wild_card);
+                let key = sort_key(&hand.cards.clone().try_into().unwrap(), has_wild_card);
+                (hand, key)
+            })
+            .collect::<Vec<(Hand, u32)>>();
+        for (hand, _) in ranked.iter() {
+            assert_eq!(
+                hand.hand_type,
+                reference_hand_type(&hand.cards, has_wild_card),
+                "{:?}",
+                hand
+            );
+        }
+        for (a, a_key) in ranked.iter() {
+            for (b, b_key) in ranked.iter() {
+                assert_eq!(a.cmp(b), a_key.cmp(b_key), "{:?} {:?}", a, b);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_total_winnings() {
+    let contents = "32T3K 765\nT55J5 684\nKK677 28\nKTJJT 220\nQQQJA 483";
+    let keys = parse_sort_keys(contents).unwrap();
+    assert_eq!(
+        total_winnings(keys.iter().map(|(key, _, bid)| (*key, *bid)).collect()),
+        6440
+    );
+    assert_eq!(
+        total_winnings(keys.iter().map(|(_, key, bid)| (*key, *bid)).collect()),
+        5905
+    );
+}
+
+#[test]
+fn test_compare_hands() {
+    let a = Hand::new("T55J5".to_string(), 0, true);
+    let b = Hand::new("KTJJT".to_string(), 0, true);
+    let comparison = compare_hands(&a, &b);
+    assert_eq!(
+        comparison,
+        Comparison {
+            hands: [
+                HandExplanation {
+                    hand_type: HandType::FourOfKind,
+                    joker_as: Some(Card::Five),
+                },
+                HandExplanation {
+                    hand_type: HandType::FourOfKind,
+                    joker_as: Some(Card::Ten),
+                },
+            ],
+            ordering: Ordering::Less,
+            tie_break: Some(0),
+        }
+    );
+    assert_eq!(
+        format_comparison(&a, &b, &comparison),
+        "  T55J5: FourOfKind (J as 5)\n  KTJJT: FourOfKind (J as T)\n  \
+         KTJJT wins: same type, card 1 K beats T\n"
+    );
+
+    let a = Hand::new("T55J5".to_string(), 0, false);
+    let b = Hand::new("KTJJT".to_string(), 0, false);
+    let comparison = compare_hands(&a, &b);
+    assert_eq!(comparison.ordering, Ordering::Greater);
+    assert_eq!(comparison.tie_break, None);
+    assert_eq!(
+        format_comparison(&a, &b, &comparison),
+        "  T55J5: ThreeOfKind\n  KTJJT: TwoPair\n  T55J5 wins: ThreeOfKind beats TwoPair\n"
+    );
+
+    // jokers lose ties to every other card
+    let a = Hand::new("JKKK2".to_string(), 0, true);
+    let b = Hand::new("QQQQ2".to_string(), 0, true);
+    let comparison = compare_hands(&a, &b);
+    assert_eq!(comparison.ordering, Ordering::Less);
+    assert_eq!(comparison.tie_break, Some(0));
+
+    let a = Hand::new("JJJJJ".to_string(), 0, true);
+    assert_eq!(compare_hands(&a, &a).hands[0].joker_as, None);
+    assert_eq!(compare_hands(&a, &a).ordering, Ordering::Equal);
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(Card::try_from('X'), Err(ParseHandError::UnknownCard('X')));
+    assert_eq!("KK".parse::<Card>(), Err(ParseHandError::WrongLength(2)));
+    assert_eq!("K".parse::<Card>(), Ok(Card::K));
+    assert_eq!("J".parse::<Card>(), Ok(Card::J));
+    assert_eq!(Card::J.as_joker(), Card::Joker);
+    assert_eq!(Card::Joker.to_string(), "J");
+
+    assert_eq!("T55J".parse::<Hand>(), Err(ParseHandError::WrongLength(4)));
+    assert_eq!(
+        "T55J55 1".parse::<Hand>(),
+        Err(ParseHandError::WrongLength(6))
+    );
+    assert_eq!(
+        "T55X5 1".parse::<Hand>(),
+        Err(ParseHandError::UnknownCard('X'))
+    );
+    assert_eq!(
+        "T55J5 x".parse::<Hand>(),
+        Err(ParseHandError::InvalidBid("x".to_string()))
+    );
+    assert!(parse_sort_keys("32T3K 765\nT55J 684").is_err());
+}
+
+#[test]
+fn test_display_round_trip() {
+    let mut rng = Rng::new(0x1234_5678_9abc_def1);
+    let labels = "23456789TJQKA".chars().collect::<Vec<char>>();
+    for _ in 0..1000 {
+        let cards = (0..5)
+            .map(|_| Card::try_from(labels[rng.below(labels.len())]).unwrap())
+            .collect::<Vec<Card>>();
+        let hand = Hand::from_cards(cards, rng.below(1000) as i64);
+        assert_eq!(hand.to_string().parse::<Hand>(), Ok(hand.clone()));
+
+        let hand = hand.with_jokers();
+        assert_eq!(
+            hand.to_string().parse::<Hand>().map(Hand::with_jokers),
+            Ok(hand.clone())
+        );
+    }
+    assert_eq!(
+        "T55J5 684".parse::<Hand>().unwrap().to_string(),
+        "T55J5 684"
+    );
+    assert_eq!(
+        "T55J5 684"
+            .parse::<Hand>()
+            .unwrap()
+            .with_jokers()
+            .to_string(),
+        "T55J5 684"
+    );
+}
@@ -1,9 +1,11 @@
-use crate::utils::get_input_file_name;
-use num_integer::lcm;
+#[cfg(test)]
+use crate::utils::Rng;
+use crate::utils::{flag_value, get_input_file_name, has_flag, lcm, Int, Overflow};
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::read_to_string;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
 
 #[derive(Copy, Clone, Debug)]
 enum Instruction {
@@ -21,64 +23,244 @@ impl From<char> for Instruction {
     }
 }
 
+// Picks nodes by name: `AAA` matches exactly, `prefix:B` and `suffix:Z` match the start or end
+// of the name and anything containing `*` or `?` is a glob.
+#[derive(Clone, Debug, PartialEq)]
+enum NodeSelector {
+    Exact(String),
+    Prefix(String),
+    Suffix(String),
+    Glob(String),
+}
+
+impl FromStr for NodeSelector {
+    type Err = String;
+
+    fn from_str(selector: &str) -> Result<Self, Self::Err> {
+        let selector = if let Some(prefix) = selector.strip_prefix("prefix:") {
+            NodeSelector::Prefix(prefix.to_string())
+        } else if let Some(suffix) = selector.strip_prefix("suffix:") {
+            NodeSelector::Suffix(suffix.to_string())
+        } else if selector.contains(['*', '?']) {
+            NodeSelector::Glob(selector.to_string())
+        } else {
+            NodeSelector::Exact(selector.to_string())
+        };
+        match &selector {
+            NodeSelector::Exact(s) | NodeSelector::Prefix(s) | NodeSelector::Suffix(s)
+                if s.is_empty() =>
+            {
+                Err("empty node selector".to_string())
+            }
+            _ => Ok(selector),
+        }
+    }
+}
+
+impl NodeSelector {
+    fn matches(&self, node: &str) -> bool {
+        match self {
+            NodeSelector::Exact(name) => node == name,
+            NodeSelector::Prefix(prefix) => node.starts_with(prefix.as_str()),
+            NodeSelector::Suffix(suffix) => node.ends_with(suffix.as_str()),
+            NodeSelector::Glob(pattern) => glob_matches(pattern.as_bytes(), node.as_bytes()),
+        }
+    }
+}
+
+// `*` matches any run of characters and `?` any single one.
+fn glob_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => {
+            glob_matches(rest, name) || (!name.is_empty() && glob_matches(pattern, &name[1..]))
+        }
+        (Some((b'?', rest)), Some((_, name_rest))) => glob_matches(rest, name_rest),
+        (Some((p, rest)), Some((n, name_rest))) => p == n && glob_matches(rest, name_rest),
+        _ => false,
+    }
+}
+
+// Steps from `start` until the first node matched by `end`, or `None` once the walk is back at a
+// node it already left from at the same point in the instructions without having found one.
+fn steps_between(
+    start: &str,
+    end: &NodeSelector,
+    instructions: &[Instruction],
+    network: &HashMap<String, (String, String)>,
+) -> Option<usize> {
+    let mut states = HashSet::new();
+    let mut current_node = start;
+    for (steps, (i, instruction)) in instructions.iter().enumerate().cycle().enumerate() {
+        if !states.insert((current_node, i)) {
+            return None;
+        }
+        let (l, r) = network.get(current_node)?;
+        current_node = match instruction {
+            Instruction::Left => l,
+            Instruction::Right => r,
+        };
+        if end.matches(current_node) {
+            return Some(steps + 1);
+        }
+    }
+    None
+}
+
 fn solve_part_1(
     instructions: &[Instruction],
     network: &HashMap<String, (String, String)>,
-) -> usize {
-    let mut current_node = "AAA";
-    let mut steps = 0;
-    for instruction in instructions.iter().cycle() {
-        if let Some((l, r)) = network.get(current_node) {
-            steps += 1;
-            current_node = match instruction {
-                Instruction::Left => l,
-                Instruction::Right => r,
-            };
-            if current_node == "ZZZ" {
-                break;
-            }
+) -> Option<usize> {
+    steps_between(
+        "AAA",
+        &NodeSelector::Exact("ZZZ".to_string()),
+        instructions,
+        network,
+    )
+}
+
+// The period of a ghost that stands on an end node exactly every `period` steps and at no other
+// time, found by walking until it is back at a node it already left from at the same point in the
+// instructions. `None` for any other ghost, whose end nodes the LCM cannot combine.
+fn ghost_period(
+    start: &str,
+    end: &NodeSelector,
+    instructions: &[Instruction],
+    network: &HashMap<String, (String, String)>,
+) -> Option<usize> {
+    let mut states: HashMap<(&str, usize), usize> = HashMap::new();
+    let mut hits = vec![];
+    let mut current_node = start;
+    for (steps, (i, instruction)) in instructions.iter().enumerate().cycle().enumerate() {
+        if let Some(&cycle_start) = states.get(&(current_node, i)) {
+            // every visit after the cycle starts comes back one cycle later, so two cycles' worth
+            // of visits show whether they keep a single period
+            let cycle = steps - cycle_start;
+            let visits = hits
+                .iter()
+                .copied()
+                .filter(|&h| h > 0)
+                .chain(
+                    hits.iter()
+                        .filter(|&&h| h >= cycle_start)
+                        .map(|h| h + cycle),
+                )
+                .collect::<Vec<usize>>();
+            let period = *visits.first()?;
+            let multiples = (1..).map(|k| k * period).take_while(|&h| h < steps + cycle);
+            return (cycle % period == 0 && visits.into_iter().eq(multiples)).then_some(period);
+        }
+        states.insert((current_node, i), steps);
+        if end.matches(current_node) {
+            hits.push(steps);
         }
+        let (l, r) = network.get(current_node)?;
+        current_node = match instruction {
+            Instruction::Left => l,
+            Instruction::Right => r,
+        };
     }
-    steps
+    None
 }
 
-fn solve_part_2(instructions: &[Instruction], network: &HashMap<String, (String, String)>) -> i64 {
-    network
-        .keys()
-        .filter(|k| k.ends_with('A'))
-        .map(|node| {
-            let mut seen: HashMap<&String, i64> = HashMap::new();
-            let mut current_node = node;
-            let mut steps = 0;
-            for instruction in instructions.iter().cycle() {
-                let (l, r) = network.get(current_node).unwrap();
-                steps += 1;
-                current_node = match instruction {
+// Steps until every ghost, one starting on each node matched by `start`, stands on a node matched
+// by `end` at the same time, as the LCM of their periods. `None` when some ghost has no period,
+// see `ghost_period`, in which case only `simulate_ghosts` can answer.
+fn ghosts_meet(
+    start: &NodeSelector,
+    end: &NodeSelector,
+    instructions: &[Instruction],
+    network: &HashMap<String, (String, String)>,
+) -> Result<Option<Int>, Overflow> {
+    let mut steps = Int::from(1);
+    for node in network.keys().filter(|k| start.matches(k)) {
+        match ghost_period(node, end, instructions, network) {
+            Some(period) => steps = lcm(&steps, &Int::from(period as i64))?,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(steps))
+}
+
+const SIMULATION_BUDGET: usize = 10_000_000;
+
+// Moves all ghosts together, one step at a time, for at most `budget` steps. Nodes are interned
+// so the ghosts' positions and the end nodes are bitsets; ghosts that land on the same node stay
+// together from then on.
+fn simulate_ghosts(
+    start: &NodeSelector,
+    end: &NodeSelector,
+    instructions: &[Instruction],
+    network: &HashMap<String, (String, String)>,
+    budget: usize,
+) -> Option<usize> {
+    let mut names = network
+        .iter()
+        .flat_map(|(node, (l, r))| [node, l, r])
+        .collect::<Vec<&String>>();
+    names.sort();
+    names.dedup();
+    let id = |name: &String| names.binary_search(&name).unwrap();
+    let edges = names
+        .iter()
+        .map(|name| network.get(*name).map(|(l, r)| [id(l), id(r)]))
+        .collect::<Vec<Option<[usize; 2]>>>();
+
+    let words = names.len().div_ceil(64);
+    let bitset = |select: &NodeSelector| {
+        let mut set = vec![0u64; words];
+        for (i, name) in names.iter().enumerate() {
+            if select.matches(name) {
+                set[i / 64] |= 1 << (i % 64);
+            }
+        }
+        set
+    };
+    let ends = bitset(end);
+    let mut positions = bitset(start);
+
+    for (steps, instruction) in instructions.iter().cycle().take(budget).enumerate() {
+        let mut next = vec![0u64; words];
+        for (word, &bits) in positions.iter().enumerate() {
+            let mut bits = bits;
+            while bits != 0 {
+                let i = word * 64 + bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                let [l, r] = edges[i]?;
+                let j = match instruction {
                     Instruction::Left => l,
                     Instruction::Right => r,
                 };
-                if current_node.ends_with('Z') {
-                    // make sure this is indeed the last end node in the chain by going through the
-                    // same chain again.
-                    if let Some(v) = seen.get(current_node) {
-                        if *v == steps {
-                            break;
-                        }
-                    } else {
-                        seen.insert(current_node, steps);
-                        steps = 0;
-                    }
-                }
+                next[j / 64] |= 1 << (j % 64);
             }
-            steps
-        })
-        .fold(1, lcm)
+        }
+        positions = next;
+        if positions.iter().zip(&ends).all(|(p, e)| p & !e == 0) {
+            return Some(steps + 1);
+        }
+    }
+    None
 }
 
-pub fn solve() -> Result<(), Error> {
-    let input_file_name = get_input_file_name(module_path!());
-    let contents = read_to_string(format!("src/{}", input_file_name))?;
+// Ghosts whose end node visits are not evenly spaced are simulated instead, for at most
+// `SIMULATION_BUDGET` steps.
+fn solve_part_2(
+    instructions: &[Instruction],
+    network: &HashMap<String, (String, String)>,
+) -> Result<Option<Int>, Overflow> {
+    let (start, end) = (
+        NodeSelector::Suffix("A".to_string()),
+        NodeSelector::Suffix("Z".to_string()),
+    );
+    Ok(
+        ghosts_meet(&start, &end, instructions, network)?.or_else(|| {
+            simulate_ghosts(&start, &end, instructions, network, SIMULATION_BUDGET)
+                .map(|steps| Int::from(steps as i64))
+        }),
+    )
+}
 
+fn parse_network(contents: &str) -> (Vec<Instruction>, HashMap<String, (String, String)>) {
     let (instructions_str, network_str) = contents.split_once("\n\n").unwrap();
 
     let instructions: Vec<Instruction> = instructions_str.chars().map(|c| c.into()).collect();
@@ -95,15 +277,99 @@ pub fn solve() -> Result<(), Error> {
             )
         })
         .collect();
+    (instructions, network)
+}
+
+// Every node a ghost starting at `start` visits before it is back at a node it already left
+// from at the same point in the instructions, which covers its whole cycle.
+fn ghost_path<'a>(
+    start: &'a str,
+    instructions: &[Instruction],
+    network: &'a HashMap<String, (String, String)>,
+) -> HashSet<&'a str> {
+    let mut visited = HashSet::from([start]);
+    let mut states = HashSet::new();
+    let mut current_node = start;
+    for (i, instruction) in instructions.iter().enumerate().cycle() {
+        if !states.insert((current_node, i)) {
+            break;
+        }
+        let Some((l, r)) = network.get(current_node) else {
+            break;
+        };
+        current_node = match instruction {
+            Instruction::Left => l,
+            Instruction::Right => r,
+        };
+        visited.insert(current_node);
+    }
+    visited
+}
+
+// The network as a Graphviz digraph. Start nodes (`..A`) are green, end nodes (`..Z`) red and
+// nodes on `path` are shaded blue.
+fn network_to_dot(network: &HashMap<String, (String, String)>, path: &HashSet<&str>) -> String {
+    let sorted = network
+        .iter()
+        .collect::<BTreeMap<&String, &(String, String)>>();
+    let mut nodes = sorted
+        .iter()
+        .flat_map(|(node, (l, r))| [node.as_str(), l.as_str(), r.as_str()])
+        .collect::<Vec<&str>>();
+    nodes.sort();
+    nodes.dedup();
+
+    let mut dot = String::from("digraph network {\n");
+    for node in nodes {
+        let mut attributes = vec![];
+        if node.ends_with('A') {
+            attributes.push("shape=doublecircle, style=filled, fillcolor=palegreen");
+        } else if node.ends_with('Z') {
+            attributes.push("shape=doublecircle, style=filled, fillcolor=lightcoral");
+        } else if path.contains(node) {
+            attributes.push("style=filled, fillcolor=lightblue");
+        }
+        if path.contains(node) {
+            attributes.push("penwidth=2");
+        }
+        if !attributes.is_empty() {
+            dot.push_str(&format!("  \"{}\" [{}];\n", node, attributes.join(", ")));
+        }
+    }
+    for (node, (l, r)) in sorted {
+        if l == r {
+            dot.push_str(&format!("  \"{}\" -> \"{}\" [label=\"LR\"];\n", node, l));
+        } else {
+            dot.push_str(&format!("  \"{}\" -> \"{}\" [label=\"L\"];\n", node, l));
+            dot.push_str(&format!("  \"{}\" -> \"{}\" [label=\"R\"];\n", node, r));
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
 
-    let part_1_solution = solve_part_1(&instructions, &network);
+pub fn solve() -> Result<(), Error> {
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    let (instructions, network) = parse_network(&contents);
+
+    let part_1_solution = solve_part_1(&instructions, &network).ok_or_else(unreachable_error)?;
     println!(
         "module: {}, part 1, result: {}",
         module_path!(),
         part_1_solution
     );
 
-    let part_2_solution = solve_part_2(&instructions, &network);
+    let part_2_solution = solve_part_2(&instructions, &network)?.ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "the ghosts' end node visits are not periodic, so the LCM does not apply, and they \
+                 do not meet within {} simulated steps",
+                SIMULATION_BUDGET
+            ),
+        )
+    })?;
     println!(
         "module: {}, part 1, result: {}",
         module_path!(),
@@ -111,3 +377,296 @@ pub fn solve() -> Result<(), Error> {
     );
     Ok(())
 }
+
+fn unreachable_error() -> Error {
+    Error::new(ErrorKind::InvalidData, "end node is never reached")
+}
+
+// `steps 8 --from BBB --to suffix:Q` counts the steps from the start nodes to the end nodes.
+// With more than one start node every node gets its own ghost and they have to arrive together;
+// when the LCM does not apply they are simulated for up to `--budget` steps.
+pub fn steps(args: &[String]) -> Result<(), Error> {
+    let selector = |flag: &str| {
+        flag_value(args, flag)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("missing {}", flag)))?
+            .parse::<NodeSelector>()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+    };
+    let (start, end) = (selector("--from")?, selector("--to")?);
+    let budget = match flag_value(args, "--budget") {
+        Some(budget) => budget.parse::<usize>().map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid --budget {:?}", budget),
+            )
+        })?,
+        None => SIMULATION_BUDGET,
+    };
+
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    let (instructions, network) = parse_network(&contents);
+
+    let starts = network
+        .keys()
+        .filter(|k| start.matches(k))
+        .collect::<Vec<&String>>();
+    let steps = match starts[..] {
+        [] => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "no node matches --from",
+            ))
+        }
+        [node] => {
+            steps_between(node, &end, &instructions, &network).map(|steps| Int::from(steps as i64))
+        }
+        _ => ghosts_meet(&start, &end, &instructions, &network)?.or_else(|| {
+            simulate_ghosts(&start, &end, &instructions, &network, budget)
+                .map(|steps| Int::from(steps as i64))
+        }),
+    };
+    println!("{}", steps.ok_or_else(unreachable_error)?);
+    Ok(())
+}
+
+// `export 8 --dot [--path AAA]` prints the network as a DOT graph, shading the nodes the ghost
+// starting at `AAA` visits.
+pub fn export(args: &[String]) -> Result<(), Error> {
+    if !has_flag(args, "--dot") {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "only --dot export is supported",
+        ));
+    }
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    let (instructions, network) = parse_network(&contents);
+
+    let path = match flag_value(args, "--path") {
+        Some(start) if !network.contains_key(start) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown node {:?}", start),
+            ))
+        }
+        Some(start) => ghost_path(start, &instructions, &network),
+        None => HashSet::new(),
+    };
+    print!("{}", network_to_dot(&network, &path));
+    Ok(())
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "LR
+
+AAA = (AAB, XXX)
+AAB = (XXX, AAZ)
+AAZ = (AAB, XXX)
+BBA = (BBB, XXX)
+BBB = (BBC, BBC)
+BBC = (BBZ, BBZ)
+BBZ = (BBB, BBB)
+XXX = (XXX, XXX)";
+
+#[test]
+fn test_node_selector() {
+    let select = |s: &str| s.parse::<NodeSelector>().unwrap();
+    assert_eq!(select("AAA"), NodeSelector::Exact("AAA".to_string()));
+    assert!(select("AAA").matches("AAA"));
+    assert!(!select("AAA").matches("AAB"));
+    assert!(select("prefix:BB").matches("BBZ"));
+    assert!(!select("prefix:BB").matches("ABB"));
+    assert!(select("suffix:Z").matches("AAZ"));
+    assert!(select("*Z").matches("AAZ"));
+    assert!(select("B?C").matches("BBC"));
+    assert!(!select("B?C").matches("BC"));
+    assert!(select("*").matches("XXX"));
+    assert!(!select("A*B").matches("AAZ"));
+    assert!("suffix:".parse::<NodeSelector>().is_err());
+}
+
+#[test]
+fn test_steps_between() {
+    let (instructions, network) = parse_network(EXAMPLE);
+    let select = |s: &str| s.parse::<NodeSelector>().unwrap();
+    assert_eq!(solve_part_1(&instructions, &network), None);
+    assert_eq!(
+        steps_between("AAA", &select("AAZ"), &instructions, &network),
+        Some(2)
+    );
+    assert_eq!(
+        steps_between("BBB", &select("suffix:Z"), &instructions, &network),
+        Some(2)
+    );
+    assert_eq!(
+        steps_between("BBB", &select("suffix:Q"), &instructions, &network),
+        None
+    );
+    assert_eq!(
+        solve_part_2(&instructions, &network),
+        Ok(Some(Int::from(6)))
+    );
+    assert_eq!(
+        ghosts_meet(&select("*A"), &select("XXX"), &instructions, &network),
+        Ok(None)
+    );
+    // starting at BBB, renamed BBA, the ghost reaches BBZ after 2 steps and then every 3, which
+    // the LCM cannot use
+    let example = EXAMPLE.replace("BBA", "BBD").replace("BBB", "BBA");
+    let (instructions, network) = parse_network(&example);
+    assert_eq!(
+        ghosts_meet(&select("*A"), &select("*Z"), &instructions, &network),
+        Ok(None)
+    );
+    assert_eq!(
+        solve_part_2(&instructions, &network),
+        Ok(Some(Int::from(2)))
+    );
+}
+
+#[test]
+fn test_ghosts_meet_against_simulation() {
+    let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+    let (start, end) = (
+        NodeSelector::Suffix("A".to_string()),
+        NodeSelector::Suffix("Z".to_string()),
+    );
+    let mut disagreements = vec![];
+    let mut analytical = 0;
+    for _ in 0..500 {
+        let names = (0..2 + rng.below(8))
+            .map(|i| {
+                format!(
+                    "{}{}",
+                    (b'B' + i as u8) as char,
+                    ['A', 'B', 'Z'][rng.below(3)]
+                )
+            })
+            .collect::<Vec<String>>();
+        let network = names
+            .iter()
+            .map(|name| {
+                let (l, r) = (
+                    &names[rng.below(names.len())],
+                    &names[rng.below(names.len())],
+                );
+                (name.clone(), (l.clone(), r.clone()))
+            })
+            .collect::<HashMap<String, (String, String)>>();
+        let instructions = (0..1 + rng.below(4))
+            .map(|_| ['L', 'R'][rng.below(2)].into())
+            .collect::<Vec<Instruction>>();
+
+        // `ghosts_meet` assumes every ghost stands on an end node exactly every `period` steps
+        // and at no other time, so only networks where that holds are compared. A ghost's walk
+        // has at most 9 nodes times 4 instructions states, so 200 steps go round its cycle twice.
+        let lcm_applies = |node: &String| {
+            let mut current_node = node;
+            let hits = instructions
+                .iter()
+                .cycle()
+                .take(200)
+                .enumerate()
+                .filter_map(|(steps, instruction)| {
+                    let (l, r) = &network[current_node];
+                    current_node = match instruction {
+                        Instruction::Left => l,
+                        Instruction::Right => r,
+                    };
+                    end.matches(current_node).then_some(steps + 1)
+                })
+                .collect::<Vec<usize>>();
+            hits.first().is_some_and(|&period| {
+                hits.len() == 200 / period
+                    && hits.iter().enumerate().all(|(k, &h)| h == (k + 1) * period)
+            })
+        };
+        if network.keys().filter(|k| start.matches(k)).all(lcm_applies) {
+            analytical += 1;
+            let steps = ghosts_meet(&start, &end, &instructions, &network)
+                .unwrap()
+                .map(|steps| usize::try_from(steps).unwrap());
+            let simulated = simulate_ghosts(&start, &end, &instructions, &network, 1000);
+            if simulated != steps {
+                disagreements.push(format!(
+                    "{:?} {:?}: lcm {:?} simulated {:?}",
+                    instructions, network, steps, simulated
+                ));
+            }
+        } else if let Ok(Some(steps)) = ghosts_meet(&start, &end, &instructions, &network) {
+            disagreements.push(format!(
+                "{:?} {:?}: lcm {} without a period",
+                instructions, network, steps
+            ));
+        }
+    }
+    assert!(disagreements.is_empty(), "{:#?}", disagreements);
+    assert!(
+        analytical > 50,
+        "only {} networks had an LCM answer",
+        analytical
+    );
+}
+
+#[test]
+fn test_simulate_ghosts() {
+    let (instructions, network) = parse_network(EXAMPLE);
+    let select = |s: &str| s.parse::<NodeSelector>().unwrap();
+    assert_eq!(
+        simulate_ghosts(&select("*A"), &select("*Z"), &instructions, &network, 100),
+        Some(6)
+    );
+    assert_eq!(
+        simulate_ghosts(&select("*A"), &select("*Z"), &instructions, &network, 5),
+        None
+    );
+    // BBB reaches BBZ after 2 steps and then every 3, which the LCM cannot use
+    assert_eq!(
+        ghosts_meet(&select("BBB"), &select("*Z"), &instructions, &network),
+        Ok(None)
+    );
+    assert_eq!(
+        simulate_ghosts(&select("BBB"), &select("*Z"), &instructions, &network, 100),
+        Some(2)
+    );
+    assert_eq!(
+        ghosts_meet(&select("*Z"), &select("*Z"), &instructions, &network),
+        Ok(Some(Int::from(6)))
+    );
+}
+
+#[test]
+fn test_ghost_path() {
+    let (instructions, network) = parse_network(EXAMPLE);
+    assert_eq!(
+        ghost_path("AAA", &instructions, &network),
+        HashSet::from(["AAA", "AAB", "AAZ"])
+    );
+    assert_eq!(
+        ghost_path("BBA", &instructions, &network),
+        HashSet::from(["BBA", "BBB", "BBC", "BBZ"])
+    );
+}
+
+#[test]
+fn test_network_to_dot() {
+    let (instructions, network) = parse_network(EXAMPLE);
+    let dot = network_to_dot(&network, &ghost_path("AAA", &instructions, &network));
+    let lines = dot.lines().collect::<Vec<&str>>();
+    assert_eq!(lines[0], "digraph network {");
+    assert_eq!(
+        lines[1],
+        "  \"AAA\" [shape=doublecircle, style=filled, fillcolor=palegreen, penwidth=2];"
+    );
+    assert_eq!(
+        lines[2],
+        "  \"AAB\" [style=filled, fillcolor=lightblue, penwidth=2];"
+    );
+    assert!(lines.contains(&"  \"BBZ\" [shape=doublecircle, style=filled, fillcolor=lightcoral];"));
+    assert!(!lines.iter().any(|line| line.starts_with("  \"XXX\" [")));
+    assert!(lines.contains(&"  \"AAA\" -> \"AAB\" [label=\"L\"];"));
+    assert!(lines.contains(&"  \"AAA\" -> \"XXX\" [label=\"R\"];"));
+    assert!(lines.contains(&"  \"BBB\" -> \"BBC\" [label=\"LR\"];"));
+    assert_eq!(lines.last(), Some(&"}"));
+}
@@ -1,40 +1,534 @@
-use crate::utils::get_input_file_name;
+use crate::utils::{add, flag_value, from_bigint, get_input_file_name, has_flag, sub, to_bigint};
+use crate::utils::{Int, Overflow};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use std::fmt;
 use std::fs::read_to_string;
-use std::io::Error;
-
-fn solve_history(mut history: Vec<i64>) -> (i64, i64) {
-    let mut first_values: Vec<i64> = vec![];
-
-    let mut last_value = *history.last().unwrap();
-    let mut first_value = history[0];
-    first_values.push(first_value);
-    while !history.iter().all(|x| *x == 0) {
-        history = history
-            .windows(2)
-            .map(|slice| slice[1] - slice[0])
-            .collect();
-        last_value += *history.last().unwrap();
-        first_value = history[0];
-        first_values.push(first_value);
-    }
-    (
-        first_values.iter().rev().fold(0, |acc, e| e - acc),
-        last_value,
-    )
+use std::io::{Error, ErrorKind};
+
+#[derive(Debug, PartialEq)]
+enum HistoryError {
+    Empty,
+    InvalidNumber(String),
+    // The differences ran out before reaching a row of zeros, so nothing shows the history is a
+    // polynomial. A polynomial through it needs at least `residual_degree`, the depth of the last
+    // difference, `residual`.
+    NotConverging {
+        residual_degree: usize,
+        residual: Int,
+    },
+    Overflow,
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryError::Empty => write!(f, "empty history"),
+            HistoryError::InvalidNumber(n) => write!(f, "invalid number {:?}", n),
+            HistoryError::NotConverging {
+                residual_degree,
+                residual,
+            } => write!(
+                f,
+                "differences never reach zero, {} is left at degree {}",
+                residual, residual_degree
+            ),
+            HistoryError::Overflow => write!(f, "differences overflow"),
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+impl From<Overflow> for HistoryError {
+    fn from(_: Overflow) -> Self {
+        HistoryError::Overflow
+    }
+}
+
+// A history and its rows of differences down to the row of zeros. `offset` is the index of the
+// first value in every row, which goes negative once the table is extended backward.
+#[derive(Clone, Debug, PartialEq)]
+struct DifferenceTable {
+    rows: Vec<Vec<Int>>,
+    offset: i64,
+}
+
+impl DifferenceTable {
+    fn new(history: &[Int]) -> Result<Self, HistoryError> {
+        if history.is_empty() {
+            return Err(HistoryError::Empty);
+        }
+        let zero = Int::from(0);
+        let mut rows = vec![history.to_vec()];
+        while !rows.last().unwrap().iter().all(|x| *x == zero) {
+            let last = rows.last().unwrap();
+            if let [residual] = &last[..] {
+                return Err(HistoryError::NotConverging {
+                    residual_degree: rows.len() - 1,
+                    residual: residual.to_owned(),
+                });
+            }
+            let differences = last
+                .windows(2)
+                .map(|slice| sub(&slice[1], &slice[0]))
+                .collect::<Result<Vec<Int>, Overflow>>()?;
+            rows.push(differences);
+        }
+        Ok(DifferenceTable { rows, offset: 0 })
+    }
+
+    // Adds `k` values to the end of every row, starting from the zeros at the bottom.
+    fn extend_forward(&mut self, k: usize) -> Result<(), Overflow> {
+        for _ in 0..k {
+            let mut below = Int::from(0);
+            for row in self.rows.iter_mut().rev() {
+                below = add(row.last().unwrap(), &below)?;
+                row.push(below.to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    // Adds `k` values to the start of every row, starting from the zeros at the bottom. The new
+    // values are collected nearest first and put in front of each row once at the end.
+    fn extend_backward(&mut self, k: usize) -> Result<(), Overflow> {
+        let offset = i64::try_from(k)
+            .ok()
+            .and_then(|k| self.offset.checked_sub(k))
+            .ok_or(Overflow)?;
+        let mut fronts = vec![Vec::with_capacity(k); self.rows.len()];
+        for _ in 0..k {
+            let mut below = Int::from(0);
+            for (row, front) in self.rows.iter().zip(fronts.iter_mut()).rev() {
+                below = sub(front.last().unwrap_or(&row[0]), &below)?;
+                front.push(below.to_owned());
+            }
+        }
+        for (row, front) in self.rows.iter_mut().zip(fronts) {
+            row.splice(0..0, front.into_iter().rev());
+        }
+        self.offset = offset;
+        Ok(())
+    }
+
+    // The history's value at `index`, where the first value given is at index 0. It comes from
+    // Newton's formula at the first value of every row, so the table does not grow however far
+    // away `index` is.
+    fn value_at(&self, index: i64) -> Result<Int, Overflow> {
+        let polynomial = Polynomial {
+            differences: self.rows.iter().map(|row| row[0].to_owned()).collect(),
+        };
+        polynomial.evaluate(i128::from(index) - i128::from(self.offset))
+    }
 }
+
+// The polynomial through a history's values, with f(n) the value at index n. It is kept in
+// Newton's forward difference form, f(n) = sum of Δ^j f(0) * C(n, j), where Δ^j f(0) is the first
+// value in row j of the difference table.
+#[derive(Clone, Debug, PartialEq)]
+struct Polynomial {
+    differences: Vec<Int>,
+}
+
+impl Polynomial {
+    fn fit(history: &[Int]) -> Result<Self, HistoryError> {
+        let mut differences = DifferenceTable::new(history)?
+            .rows
+            .into_iter()
+            .filter_map(|mut row| (!row.is_empty()).then(|| row.swap_remove(0)))
+            .collect::<Vec<Int>>();
+        while differences.last() == Some(&Int::from(0)) {
+            differences.pop();
+        }
+        Ok(Polynomial { differences })
+    }
+
+    // `None` for the zero polynomial.
+    fn degree(&self) -> Option<usize> {
+        self.differences.len().checked_sub(1)
+    }
+
+    // A term can be up to C(n, n / 2) times larger than any difference, so the sum is taken over
+    // BigInt and only the value itself has to fit.
+    fn evaluate(&self, n: i128) -> Result<Int, Overflow> {
+        // C(n, j + 1) = C(n, j) * (n - j) / (j + 1), which divides exactly for negative n too
+        let mut binomial = BigInt::from(1);
+        let mut value = BigInt::from(0);
+        for (j, difference) in self.differences.iter().enumerate() {
+            value += to_bigint(difference) * &binomial;
+            binomial = binomial * (n - j as i128) / (j as i128 + 1);
+        }
+        from_bigint(value)
+    }
+
+    // The coefficient of n^i at index i.
+    fn coefficients(&self) -> Vec<BigRational> {
+        let mut coefficients =
+            vec![BigRational::from_integer(BigInt::from(0)); self.differences.len()];
+        // n (n - 1) ... (n - j + 1), lowest power first
+        let mut falling_factorial = vec![BigInt::from(1)];
+        let mut factorial = BigInt::from(1);
+        for (j, difference) in self.differences.iter().enumerate() {
+            for (i, c) in falling_factorial.iter().enumerate() {
+                coefficients[i] += BigRational::new(to_bigint(difference) * c, factorial.clone());
+            }
+            let mut next = vec![BigInt::from(0); falling_factorial.len() + 1];
+            for (i, c) in falling_factorial.iter().enumerate() {
+                next[i + 1] += c;
+                next[i] -= c * j;
+            }
+            falling_factorial = next;
+            factorial *= j + 1;
+        }
+        coefficients
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let zero = BigRational::from_integer(BigInt::from(0));
+        let one = BigRational::from_integer(BigInt::from(1));
+        let mut terms = self
+            .coefficients()
+            .into_iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, c)| *c != zero)
+            .peekable();
+        if terms.peek().is_none() {
+            return write!(f, "0");
+        }
+        for (i, (power, c)) in terms.enumerate() {
+            let negative = c < zero;
+            match (i, negative) {
+                (0, false) => {}
+                (0, true) => write!(f, "-")?,
+                (_, false) => write!(f, " + ")?,
+                (_, true) => write!(f, " - ")?,
+            }
+            let magnitude = if negative { -c } else { c };
+            match (power, magnitude == one) {
+                (0, _) => write!(f, "{}", magnitude)?,
+                (_, true) => write!(f, "n")?,
+                (_, false) => write!(f, "{} n", magnitude)?,
+            }
+            if power > 1 {
+                write!(f, "^{}", power)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn solve_history(history: Vec<Int>) -> Result<(Int, Int), HistoryError> {
+    let polynomial = Polynomial::fit(&history)?;
+    Ok((
+        polynomial.evaluate(-1)?,
+        polynomial.evaluate(history.len() as i128)?,
+    ))
+}
+
+fn parse_history(line: &str) -> Result<Vec<Int>, HistoryError> {
+    let history = line
+        .split_whitespace()
+        .map(|n| {
+            n.parse()
+                .map_err(|_| HistoryError::InvalidNumber(n.to_string()))
+        })
+        .collect::<Result<Vec<Int>, HistoryError>>()?;
+    if history.is_empty() {
+        return Err(HistoryError::Empty);
+    }
+    Ok(history)
+}
+
+fn parse_histories(contents: &str) -> Result<Vec<Vec<Int>>, Error> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| parse_history(line).map_err(|e| line_error(i, e)))
+        .collect()
+}
+
+fn line_error(i: usize, e: HistoryError) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("line {}: {}", i + 1, e))
+}
+
 pub fn solve() -> Result<(), Error> {
     let input_file_name = get_input_file_name(module_path!());
     let contents = read_to_string(format!("src/{}", input_file_name))?;
-    let solution: (i64, i64) = contents
-        .lines()
-        .map(|line| {
-            let nums: Vec<i64> = line.split(' ').map(|t| t.parse().unwrap()).collect();
-            solve_history(nums)
+    let (mut sum_first, mut sum_last) = (Int::from(0), Int::from(0));
+    for (i, history) in parse_histories(&contents)?.into_iter().enumerate() {
+        let (first, last) = solve_history(history).map_err(|e| line_error(i, e))?;
+        sum_first = add(&sum_first, &first)?;
+        sum_last = add(&sum_last, &last)?;
+    }
+    println!("module: {}, part 1, result: {}", module_path!(), sum_last);
+    println!("module: {}, part 2, result: {}", module_path!(), sum_first);
+    Ok(())
+}
+
+// `extrapolate 9 --ahead 10 --behind 2` prints every history extended by 10 values forward and
+// 2 backward, `--at -5` just the value at that index and `--table` the whole difference table.
+pub fn extrapolate(args: &[String]) -> Result<(), Error> {
+    let number = |flag: &str, default: i64| match flag_value(args, flag) {
+        Some(n) => n
+            .parse::<i64>()
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, format!("invalid {} {:?}", flag, n))),
+        None => Ok(default),
+    };
+    let steps = |flag: &str, default: i64| {
+        usize::try_from(number(flag, default)?).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} must not be negative", flag),
+            )
         })
-        .fold((0, 0), |(acc_first, acc_last), (first, last)| {
-            (acc_first + first, acc_last + last)
-        });
-    println!("module: {}, part 1, result: {}", module_path!(), solution.1);
-    println!("module: {}, part 2, result: {}", module_path!(), solution.0);
+    };
+    let (ahead, behind) = (steps("--ahead", 1)?, steps("--behind", 0)?);
+    let at = flag_value(args, "--at")
+        .map(|_| number("--at", 0))
+        .transpose()?;
+
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    for (i, history) in parse_histories(&contents)?.into_iter().enumerate() {
+        let mut table = DifferenceTable::new(&history).map_err(|e| line_error(i, e))?;
+        if let Some(index) = at {
+            println!("{}", table.value_at(index)?);
+            continue;
+        }
+        // reserved up front, so a huge `--ahead` or `--behind` is an error instead of an abort
+        for row in table.rows.iter_mut() {
+            row.try_reserve(ahead.saturating_add(behind))
+                .map_err(|e| Error::new(ErrorKind::OutOfMemory, e))?;
+        }
+        table.extend_forward(ahead)?;
+        table.extend_backward(behind)?;
+        if has_flag(args, "--table") {
+            for (depth, row) in table.rows.iter().enumerate() {
+                let row = row.iter().map(|v| v.to_string()).collect::<Vec<String>>();
+                println!("{}{}", "  ".repeat(depth), row.join(" "));
+            }
+            println!();
+        } else {
+            let row = table.rows[0].iter().map(|v| v.to_string());
+            println!("{}", row.collect::<Vec<String>>().join(" "));
+        }
+    }
     Ok(())
 }
+
+// `fit 9` prints the degree and formula of the polynomial through every history. With
+// `--residual` a history whose differences never reach zero reports how far they got instead of
+// stopping the whole run.
+pub fn fit(args: &[String]) -> Result<(), Error> {
+    let input_file_name = get_input_file_name(module_path!());
+    let contents = read_to_string(format!("src/{}", input_file_name))?;
+    for (i, history) in parse_histories(&contents)?.into_iter().enumerate() {
+        let polynomial = match Polynomial::fit(&history) {
+            Ok(polynomial) => polynomial,
+            Err(HistoryError::NotConverging {
+                residual_degree, ..
+            }) if has_flag(args, "--residual") => {
+                println!("residual degree {}: not a polynomial", residual_degree);
+                continue;
+            }
+            Err(e) => return Err(line_error(i, e)),
+        };
+        match polynomial.degree() {
+            Some(degree) => println!("degree {}: {}", degree, polynomial),
+            None => println!("zero: 0"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "0 3 6 9 12 15
+1 3 6 10 15 21
+10 13 16 21 30 45";
+
+#[cfg(test)]
+fn ints(values: &[i64]) -> Vec<Int> {
+    values.iter().map(|v| Int::from(*v)).collect()
+}
+
+#[test]
+fn test_solve_history() {
+    let solutions = parse_histories(EXAMPLE)
+        .unwrap()
+        .into_iter()
+        .map(|history| solve_history(history).unwrap())
+        .collect::<Vec<(Int, Int)>>();
+    let expected =
+        [(-3, 18), (0, 28), (5, 68)].map(|(first, last)| (Int::from(first), Int::from(last)));
+    assert_eq!(solutions, expected);
+}
+
+#[test]
+fn test_solve_history_large_terms() {
+    // every difference fits in an i64, but Δ^j f(0) * C(22, j) does not for the middle rows
+    let mut history = vec![Int::from(14_000_000_000_000i64); 22];
+    history[1..21].fill(Int::from(0));
+    let table = DifferenceTable::new(&history).unwrap();
+    assert_eq!(table.value_at(22), Ok(Int::from(294_000_000_000_000i64)));
+    assert_eq!(
+        solve_history(history),
+        Ok((
+            Int::from(294_000_000_000_000i64),
+            Int::from(294_000_000_000_000i64)
+        ))
+    );
+}
+
+#[test]
+fn test_difference_table() {
+    let mut table = DifferenceTable::new(&ints(&[1, 3, 6, 10, 15, 21])).unwrap();
+    assert_eq!(
+        table.rows,
+        vec![
+            ints(&[1, 3, 6, 10, 15, 21]),
+            ints(&[2, 3, 4, 5, 6]),
+            ints(&[1, 1, 1, 1]),
+            ints(&[0, 0, 0]),
+        ]
+    );
+    table.extend_forward(2).unwrap();
+    table.extend_backward(1).unwrap();
+    assert_eq!(table.offset, -1);
+    assert_eq!(table.rows[0], ints(&[0, 1, 3, 6, 10, 15, 21, 28, 36]));
+    assert_eq!(table.rows[1], ints(&[1, 2, 3, 4, 5, 6, 7, 8]));
+    assert_eq!(table.rows[3], ints(&[0, 0, 0, 0, 0, 0]));
+}
+
+#[test]
+fn test_value_at() {
+    let table = DifferenceTable::new(&ints(&[10, 13, 16, 21, 30, 45])).unwrap();
+    assert_eq!(table.value_at(3), Ok(Int::from(21)));
+    assert_eq!(table.value_at(6), Ok(Int::from(68)));
+    assert_eq!(table.value_at(-1), Ok(Int::from(5)));
+    let mut extended = table.clone();
+    extended.extend_forward(10).unwrap();
+    extended.extend_backward(10).unwrap();
+    for index in -10..16 {
+        assert_eq!(table.value_at(index), extended.value_at(index));
+        assert_eq!(
+            extended.value_at(index).unwrap(),
+            extended.rows[0][(index + 10) as usize]
+        );
+    }
+
+    let mut identity = DifferenceTable::new(&ints(&[0, 1, 2])).unwrap();
+    identity.extend_backward(3).unwrap();
+    assert_eq!(identity.value_at(i64::MIN), Ok(Int::from(i64::MIN)));
+    assert_eq!(identity.value_at(i64::MAX), Ok(Int::from(i64::MAX)));
+    assert_eq!(identity.extend_backward(usize::MAX), Err(Overflow));
+    assert_eq!(identity.rows[0], ints(&[-3, -2, -1, 0, 1, 2]));
+}
+
+#[test]
+fn test_polynomial() {
+    let polynomials = parse_histories(EXAMPLE)
+        .unwrap()
+        .iter()
+        .map(|history| Polynomial::fit(history).unwrap())
+        .collect::<Vec<Polynomial>>();
+    assert_eq!(
+        polynomials.iter().map(|p| p.degree()).collect::<Vec<_>>(),
+        vec![Some(1), Some(2), Some(3)]
+    );
+    assert_eq!(polynomials[0].to_string(), "3 n");
+    assert_eq!(polynomials[1].to_string(), "1/2 n^2 + 3/2 n + 1");
+    assert_eq!(polynomials[2].to_string(), "1/3 n^3 - n^2 + 11/3 n + 10");
+    let fit = |history: &[i64]| Polynomial::fit(&ints(history)).unwrap();
+    assert_eq!(fit(&[0, 0, 0]).to_string(), "0");
+    assert_eq!(fit(&[0, 0, 0]).degree(), None);
+    assert_eq!(fit(&[-4, -5, -6]).to_string(), "-n - 4");
+    assert_eq!(fit(&[7, 7]).to_string(), "7");
+}
+
+#[test]
+fn test_polynomial_matches_difference_table() {
+    for history in parse_histories(EXAMPLE).unwrap().into_iter().chain([
+        ints(&[5, 4, 9, 26, 61, 120, 209]),
+        ints(&[1, 1]),
+        ints(&[0]),
+    ]) {
+        let polynomial = Polynomial::fit(&history).unwrap();
+        let mut table = DifferenceTable::new(&history).unwrap();
+        table.extend_forward(20).unwrap();
+        table.extend_backward(12).unwrap();
+        let coefficients = polynomial.coefficients();
+        for n in -12i64..20 {
+            let value = &table.rows[0][(n + 12) as usize];
+            assert_eq!(
+                polynomial.evaluate(n.into()),
+                Ok(value.to_owned()),
+                "{:?} at {}",
+                history,
+                n
+            );
+            let formula = coefficients
+                .iter()
+                .rev()
+                .fold(BigRational::from_integer(BigInt::from(0)), |acc, c| {
+                    acc * BigInt::from(n) + c
+                });
+            assert_eq!(formula, BigRational::from_integer(to_bigint(value)));
+        }
+    }
+}
+
+#[test]
+fn test_history_errors() {
+    assert_eq!(parse_history("  0\t3   6 9 "), Ok(ints(&[0, 3, 6, 9])));
+    assert_eq!(parse_history("   "), Err(HistoryError::Empty));
+    assert_eq!(
+        parse_history("1 2 x"),
+        Err(HistoryError::InvalidNumber("x".to_string()))
+    );
+    assert_eq!(DifferenceTable::new(&[]), Err(HistoryError::Empty));
+    assert_eq!(
+        solve_history(ints(&[1, 2, 4])),
+        Err(HistoryError::NotConverging {
+            residual_degree: 2,
+            residual: Int::from(1)
+        })
+    );
+    assert_eq!(
+        Polynomial::fit(&ints(&[4])),
+        Err(HistoryError::NotConverging {
+            residual_degree: 0,
+            residual: Int::from(4)
+        })
+    );
+    assert_eq!(
+        parse_histories("0 1 2\n\n3 4 5").unwrap_err().to_string(),
+        "line 2: empty history"
+    );
+}
+
+// Without either feature the next value wraps, or panics in a debug build.
+#[cfg(any(feature = "checked", feature = "bigint"))]
+#[test]
+fn test_extrapolation_past_i64() {
+    let history = ints(&[i64::MAX - 2, i64::MAX - 1, i64::MAX]);
+    let next = solve_history(history).map(|(_, last)| last.to_string());
+    if cfg!(feature = "bigint") {
+        assert_eq!(next, Ok("9223372036854775808".to_string()));
+    } else {
+        assert_eq!(next, Err(HistoryError::Overflow));
+    }
+    assert_eq!(
+        parse_history("9223372036854775808 1").map(|history| history.len()),
+        if cfg!(feature = "bigint") {
+            Ok(2)
+        } else {
+            Err(HistoryError::InvalidNumber(
+                "9223372036854775808".to_string(),
+            ))
+        }
+    );
+}
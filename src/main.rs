@@ -1,3 +1,5 @@
+use std::io::{Error, ErrorKind};
+
 mod day1;
 mod day2;
 mod day3;
@@ -9,11 +11,44 @@ mod day8;
 mod day9;
 mod utils;
 
+fn run_command(args: &[String]) -> Result<(), Error> {
+    let command = args[0].as_str();
+    let day = args.get(1).map(String::as_str).unwrap_or_default();
+    match (command, day) {
+        ("analyze", "2") => day2::analyze(),
+        ("show", "3") => day3::show(),
+        ("cascade", "4") => day4::cascade_report(&args[2..]),
+        ("score", "4") => day4::score(&args[2..]),
+        ("check-input", "5") => day5::check_input(),
+        ("almanac", "map") => day5::map_command(&args[2..]),
+        ("almanac", "trace") => day5::trace_command(&args[2..]),
+        ("almanac", "svg") => day5::svg_command(&args[2..]),
+        ("race", "6") => day6::race_command(&args[2..]),
+        ("explain", "7") => day7::explain(&args[2..]),
+        ("export", "8") => day8::export(&args[2..]),
+        ("steps", "8") => day8::steps(&args[2..]),
+        ("extrapolate", "9") => day9::extrapolate(&args[2..]),
+        ("fit", "9") => day9::fit(&args[2..]),
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("unknown command: {}", args.join(" ")),
+        )),
+    }
+}
+
 pub fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = run_command(&args) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     day1::solve();
     day2::solve();
-    day3::solve();
-    day4::solve();
+    let _ = day3::solve();
+    let _ = day4::solve();
     let _ = day5::solve();
     let _ = day6::solve();
     let _ = day7::solve();
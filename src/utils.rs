@@ -1,3 +1,133 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
 pub fn get_input_file_name(path: &str) -> String {
     path.split("::").last().unwrap().trim_end().to_string() + "_input.txt"
-}
\ No newline at end of file
+}
+
+pub fn has_flag(args: &[String], flag: &str) -> bool {
+    args.iter().any(|arg| arg == flag)
+}
+
+// The value following `flag`, as in `--rule linear`.
+pub fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+// Copy counts, sums and LCMs that can outgrow the numbers they start from. With the `bigint`
+// feature they are arbitrary-precision and never overflow.
+#[cfg(not(feature = "bigint"))]
+pub type Int = i64;
+#[cfg(feature = "bigint")]
+pub type Int = BigInt;
+
+#[derive(Debug, PartialEq)]
+pub struct Overflow;
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "arithmetic overflow")
+    }
+}
+
+impl std::error::Error for Overflow {}
+
+impl From<Overflow> for Error {
+    fn from(e: Overflow) -> Self {
+        Error::new(ErrorKind::InvalidData, e)
+    }
+}
+
+// `a + b`, or `None` when it does not fit, whichever features are on.
+#[cfg(not(feature = "bigint"))]
+pub fn checked_add(a: &Int, b: &Int) -> Option<Int> {
+    a.checked_add(*b)
+}
+
+#[cfg(feature = "bigint")]
+pub fn checked_add(a: &Int, b: &Int) -> Option<Int> {
+    Some(a + b)
+}
+
+// `add`, `sub`, `mul` and `lcm` only fail with the `checked` feature. Without it, and without
+// `bigint`, they are the bare `i64` operations that wrap in release builds.
+#[cfg(all(feature = "checked", not(feature = "bigint")))]
+pub fn add(a: &Int, b: &Int) -> Result<Int, Overflow> {
+    checked_add(a, b).ok_or(Overflow)
+}
+
+#[cfg(any(not(feature = "checked"), feature = "bigint"))]
+pub fn add(a: &Int, b: &Int) -> Result<Int, Overflow> {
+    Ok(a + b)
+}
+
+#[cfg(all(feature = "checked", not(feature = "bigint")))]
+pub fn sub(a: &Int, b: &Int) -> Result<Int, Overflow> {
+    a.checked_sub(*b).ok_or(Overflow)
+}
+
+#[cfg(any(not(feature = "checked"), feature = "bigint"))]
+pub fn sub(a: &Int, b: &Int) -> Result<Int, Overflow> {
+    Ok(a - b)
+}
+
+#[cfg(all(feature = "checked", not(feature = "bigint")))]
+pub fn mul(a: &Int, b: &Int) -> Result<Int, Overflow> {
+    a.checked_mul(*b).ok_or(Overflow)
+}
+
+#[cfg(any(not(feature = "checked"), feature = "bigint"))]
+pub fn mul(a: &Int, b: &Int) -> Result<Int, Overflow> {
+    Ok(a * b)
+}
+
+pub fn lcm(a: &Int, b: &Int) -> Result<Int, Overflow> {
+    mul(&(a / a.gcd(b)), b)
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn to_bigint(n: &Int) -> BigInt {
+    BigInt::from(*n)
+}
+
+#[cfg(feature = "bigint")]
+pub fn to_bigint(n: &Int) -> BigInt {
+    n.clone()
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn from_bigint(n: BigInt) -> Result<Int, Overflow> {
+    Int::try_from(n).map_err(|_| Overflow)
+}
+
+#[cfg(feature = "bigint")]
+pub fn from_bigint(n: BigInt) -> Result<Int, Overflow> {
+    Ok(n)
+}
+
+// Seeded xorshift generator, so randomized tests see the same data on every run.
+#[cfg(test)]
+pub struct Rng(u64);
+
+#[cfg(test)]
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}